@@ -1,20 +1,23 @@
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, SampleFormat, Stream, StreamConfig};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{BufferSize, Device, SampleFormat, Stream, StreamConfig};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::fs::File;
-use std::io::Cursor;
+use std::io::{Cursor, Read};
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::{CODEC_TYPE_NULL, CodecType, DecoderOptions};
+use symphonia::core::codecs::{CODEC_TYPE_NULL, CodecParameters, CodecType, DecoderOptions};
 use symphonia::core::errors::Error as SymphoniaError;
-use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadOnlySource};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
 
+use crate::devices::{InputDeviceArgs, buffer_frames, select_host, select_input_device};
+use crate::protocol::{StreamError, StreamResult};
+
 pub(crate) fn codec_name(codec: CodecType) -> &'static str {
     use symphonia::core::codecs::*;
     match codec {
@@ -44,14 +47,11 @@ pub(crate) fn connection_prefix(connection_id: usize, connection_count: usize) -
     }
 }
 
+/// Copy every chunk from `source_rx` to each of `connection_count` receivers.
 pub(crate) fn start_audio_fanout(
+    mut source_rx: mpsc::UnboundedReceiver<Vec<u8>>,
     connection_count: usize,
-) -> (
-    mpsc::UnboundedSender<Vec<u8>>,
-    Vec<mpsc::UnboundedReceiver<Vec<u8>>>,
-    JoinHandle<()>,
-) {
-    let (source_tx, mut source_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+) -> (Vec<mpsc::UnboundedReceiver<Vec<u8>>>, JoinHandle<()>) {
     let mut output_senders = Vec::with_capacity(connection_count);
     let mut output_receivers = Vec::with_capacity(connection_count);
 
@@ -71,7 +71,80 @@ pub(crate) fn start_audio_fanout(
         }
     });
 
-    (source_tx, output_receivers, fanout_task)
+    (output_receivers, fanout_task)
+}
+
+/// De-interleave the audio from `source_rx` and send channel k, as mono, to
+/// receiver k.
+pub(crate) fn start_channel_split(
+    mut source_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    channels: u16,
+    sample_bytes: usize,
+) -> (Vec<mpsc::UnboundedReceiver<Vec<u8>>>, JoinHandle<()>) {
+    let mut output_senders = Vec::with_capacity(channels as usize);
+    let mut output_receivers = Vec::with_capacity(channels as usize);
+    for _ in 0..channels {
+        let (tx, rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        output_senders.push(Some(tx));
+        output_receivers.push(rx);
+    }
+
+    let split_task = tokio::spawn(async move {
+        let mut splitter = ChannelSplitter::new(channels as usize, sample_bytes);
+        while let Some(audio_data) = source_rx.recv().await {
+            for (sender, channel_data) in output_senders.iter_mut().zip(splitter.split(&audio_data))
+            {
+                // Keep feeding the other channels if one connection has finished.
+                if sender
+                    .as_ref()
+                    .is_some_and(|tx| tx.send(channel_data).is_err())
+                {
+                    *sender = None;
+                }
+            }
+            if output_senders.iter().all(Option::is_none) {
+                break;
+            }
+        }
+    });
+
+    (output_receivers, split_task)
+}
+
+/// Splits interleaved PCM into one buffer per channel. A partial frame at the
+/// end of a chunk is held until the next chunk completes it.
+struct ChannelSplitter {
+    channels: usize,
+    sample_bytes: usize,
+    pending: Vec<u8>,
+}
+
+impl ChannelSplitter {
+    fn new(channels: usize, sample_bytes: usize) -> Self {
+        ChannelSplitter {
+            channels,
+            sample_bytes,
+            pending: Vec::new(),
+        }
+    }
+
+    fn split(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.pending.extend_from_slice(data);
+        let frame_bytes = self.channels * self.sample_bytes;
+        let whole_frames = self.pending.len() / frame_bytes * frame_bytes;
+
+        let mut outputs = vec![Vec::with_capacity(whole_frames / self.channels); self.channels];
+        for frame in self.pending[..whole_frames].chunks_exact(frame_bytes) {
+            for (output, sample) in outputs
+                .iter_mut()
+                .zip(frame.chunks_exact(self.sample_bytes))
+            {
+                output.extend_from_slice(sample);
+            }
+        }
+        self.pending.drain(..whole_frames);
+        outputs
+    }
 }
 
 pub(crate) struct AudioCapture {
@@ -81,19 +154,23 @@ pub(crate) struct AudioCapture {
 }
 
 impl AudioCapture {
-    pub(crate) fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or("No input device available")?;
+    pub(crate) fn new(input: &InputDeviceArgs) -> Result<Self, Box<dyn std::error::Error>> {
+        let host = select_host(input.host.as_deref())?;
+        let device = select_input_device(&host, input.device.as_deref())?;
 
-        println!("Input device: {}", device.name()?);
+        println!("Input device: {} ({})", device.name()?, host.id().name());
 
         let supported_config = device.default_input_config()?;
         println!("Default input config: {:?}", supported_config);
 
         let sample_format = supported_config.sample_format();
-        let config: StreamConfig = supported_config.into();
+        let supported_buffer = *supported_config.buffer_size();
+        let mut config: StreamConfig = supported_config.into();
+        if let Some(buffer_ms) = input.buffer_ms {
+            let frames = buffer_frames(buffer_ms, config.sample_rate.0, &supported_buffer)?;
+            println!("Capture buffer: {} frames ({} ms)", frames, buffer_ms);
+            config.buffer_size = BufferSize::Fixed(frames);
+        }
 
         Ok(AudioCapture {
             device,
@@ -276,6 +353,7 @@ impl AudioFileReader {
         config_tx: oneshot::Sender<(u32, u16)>,
         ready_rx: Option<oneshot::Receiver<()>>,
         fast_mode: bool,
+        looping: bool,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let repaired_wav = if self
             .path
@@ -291,35 +369,19 @@ impl AudioFileReader {
             Some(bytes) => Box::new(Cursor::new(bytes)),
             None => Box::new(File::open(&self.path)?),
         };
-        let mss = MediaSourceStream::new(source, Default::default());
 
         let mut hint = Hint::new();
         if let Some(ext) = self.path.extension() {
             hint.with_extension(ext.to_str().unwrap_or(""));
         }
-
-        let meta_opts: MetadataOptions = Default::default();
-        let fmt_opts: FormatOptions = Default::default();
-
-        let probed = symphonia::default::get_probe().format(&hint, mss, &fmt_opts, &meta_opts)?;
-
-        let mut format = probed.format;
-
-        let track = format
-            .tracks()
-            .iter()
-            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
-            .ok_or("No supported audio tracks found")?;
-
-        let track_id = track.id;
-        let codec_params = &track.codec_params;
+        let (format, track_id, codec_params) = probe_track(source, &hint)?;
 
         let sample_rate = codec_params.sample_rate.ok_or("Sample rate not found")?;
         let channels = codec_params.channels.ok_or("Channels not found")?.count() as u16;
         let total_frames = codec_params
             .n_frames
             .filter(|frames| *frames > 0)
-            .or_else(|| fallback_wav_frame_count(&self.path, codec_params));
+            .or_else(|| fallback_wav_frame_count(&self.path, &codec_params));
 
         // --- File metadata ---
         let codec_str = codec_name(codec_params.codec);
@@ -398,88 +460,281 @@ impl AudioFileReader {
             None
         };
 
-        let dec_opts: DecoderOptions = Default::default();
-        let mut decoder = symphonia::default::get_codecs().make(&codec_params, &dec_opts)?;
-
-        let mut sample_buf = None;
-        let mut frames_sent: u64 = 0;
+        tokio::task::spawn_blocking(move || {
+            let mut pacer = RealtimePacer::new();
+            let mut frames_sent: u64 = 0;
+            let result = decode_track(format, track_id, &codec_params, looping, |audio, frames| {
+                if tx.send(audio).is_err() {
+                    return false;
+                }
+                frames_sent += frames;
+
+                // Update progress bar; with --loop it restarts with each pass.
+                if let Some((ref pb, total_secs)) = pb {
+                    let position = match total_frames {
+                        Some(total) if looping => frames_sent % total,
+                        _ => frames_sent,
+                    };
+                    let current_secs = position / sample_rate as u64;
+                    pb.set_position(current_secs);
+                    pb.set_message(format!(
+                        "{}:{:02} / {}:{:02}",
+                        current_secs / 60,
+                        current_secs % 60,
+                        total_secs / 60,
+                        total_secs % 60,
+                    ));
+                }
 
-        loop {
-            let packet = match format.next_packet() {
-                Ok(packet) => packet,
-                Err(SymphoniaError::ResetRequired) => {
-                    decoder.reset();
-                    continue;
+                // If not in fast mode, simulate real-time streaming
+                if !fast_mode {
+                    pacer.wait(Duration::from_secs_f64(frames as f64 / sample_rate as f64));
                 }
-                Err(SymphoniaError::IoError(e))
-                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
-                {
+                true
+            });
+
+            if let Some((pb, _)) = pb {
+                pb.finish_and_clear();
+            }
+            result
+        })
+        .await?
+    }
+}
+
+/// Probe `source` and pick its first decodable track, returning the format
+/// reader with the track's ID and codec parameters.
+fn probe_track(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+) -> Result<(Box<dyn FormatReader>, u32, CodecParameters), StreamError> {
+    let mss = MediaSourceStream::new(source, Default::default());
+    let probed = symphonia::default::get_probe().format(
+        hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or("No supported audio tracks found")?;
+    let track_id = track.id;
+    let codec_params = track.codec_params.clone();
+    Ok((format, track_id, codec_params))
+}
+
+/// Decode `track_id` to interleaved linear16, handing each packet's audio and
+/// frame count to `send` until the source ends or `send` returns false.
+/// With `looping`, the source is rewound to the start instead of ending.
+/// Packets that fail to decode are skipped. Blocks the calling thread.
+fn decode_track(
+    mut format: Box<dyn FormatReader>,
+    track_id: u32,
+    codec_params: &CodecParameters,
+    looping: bool,
+    mut send: impl FnMut(Vec<u8>, u64) -> bool,
+) -> StreamResult {
+    let mut decoder =
+        symphonia::default::get_codecs().make(codec_params, &DecoderOptions::default())?;
+    let mut sample_buf = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::ResetRequired) => {
+                decoder.reset();
+                continue;
+            }
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                if !looping {
                     break;
                 }
-                Err(err) => return Err(Box::new(err)),
-            };
-
-            if packet.track_id() != track_id {
+                // Start the file over; the stream continues without a gap.
+                format
+                    .seek(SeekMode::Coarse, SeekTo::TimeStamp { ts: 0, track_id })
+                    .map_err(|e| format!("Failed to rewind file for --loop: {e}"))?;
+                decoder.reset();
                 continue;
             }
+            Err(err) => return Err(Box::new(err)),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
 
-            match decoder.decode(&packet) {
-                Ok(decoded) => {
-                    let frame_count = decoded.frames() as u64;
-
-                    if sample_buf.is_none() {
-                        let spec = *decoded.spec();
-                        let duration = decoded.capacity() as u64;
-                        sample_buf = Some(SampleBuffer::<i16>::new(duration, spec));
-                    }
-
-                    if let Some(buf) = &mut sample_buf {
-                        buf.copy_interleaved_ref(decoded);
-
-                        let samples = buf.samples();
-                        let mut audio_data = Vec::with_capacity(samples.len() * 2);
-
-                        for &sample in samples {
-                            audio_data.extend_from_slice(&sample.to_le_bytes());
-                        }
-
-                        if tx.send(audio_data).is_err() {
-                            break;
-                        }
-
-                        frames_sent += frame_count;
-
-                        // Update progress bar
-                        if let Some((ref pb, total_secs)) = pb {
-                            let current_secs = frames_sent / sample_rate as u64;
-                            pb.set_position(current_secs);
-                            pb.set_message(format!(
-                                "{}:{:02} / {}:{:02}",
-                                current_secs / 60,
-                                current_secs % 60,
-                                total_secs / 60,
-                                total_secs % 60,
-                            ));
-                        }
-
-                        // If not in fast mode, simulate real-time streaming
-                        if !fast_mode {
-                            let sleep_duration =
-                                Duration::from_secs_f64(frame_count as f64 / sample_rate as f64);
-                            tokio::time::sleep(sleep_duration).await;
-                        }
-                    }
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let frame_count = decoded.frames() as u64;
+                let buf = sample_buf.get_or_insert_with(|| {
+                    SampleBuffer::<i16>::new(decoded.capacity() as u64, *decoded.spec())
+                });
+                buf.copy_interleaved_ref(decoded);
+
+                let mut audio_data = Vec::with_capacity(buf.samples().len() * 2);
+                for &sample in buf.samples() {
+                    audio_data.extend_from_slice(&sample.to_le_bytes());
+                }
+                if !send(audio_data, frame_count) {
+                    break;
                 }
-                Err(SymphoniaError::IoError(_)) => continue,
-                Err(SymphoniaError::DecodeError(_)) => continue,
-                Err(err) => return Err(Box::new(err)),
             }
+            Err(SymphoniaError::IoError(_)) => continue,
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(err) => return Err(Box::new(err)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Bytes per sample for raw encodings whose data rate is fixed, or `None` for
+/// compressed encodings.
+pub(crate) fn bytes_per_sample(encoding: &str) -> Option<u32> {
+    match encoding.to_ascii_lowercase().as_str() {
+        "linear16" => Some(2),
+        "linear32" => Some(4),
+        "mulaw" | "alaw" => Some(1),
+        _ => None,
+    }
+}
+
+/// The user-declared format of raw audio read from stdin.
+pub(crate) struct RawAudioFormat {
+    pub(crate) encoding: String,
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+}
+
+/// Paces audio to real time against the wall clock, so a live source that
+/// already delivers audio at real-time rate is not delayed any further.
+struct RealtimePacer {
+    started: Instant,
+    sent: Duration,
+}
+
+impl RealtimePacer {
+    fn new() -> Self {
+        RealtimePacer {
+            started: Instant::now(),
+            sent: Duration::ZERO,
         }
+    }
 
-        if let Some((pb, _)) = pb {
-            pb.finish_and_clear();
+    /// Account for `duration` more audio, sleeping until it is due.
+    fn wait(&mut self, duration: Duration) {
+        self.sent += duration;
+        let due = self.started + self.sent;
+        let now = Instant::now();
+        if due > now {
+            std::thread::sleep(due - now);
         }
+    }
+}
 
-        Ok(())
+/// Stream audio from stdin, either forwarding raw bytes in `raw_format` or
+/// decoding a container format. Blocks the calling thread.
+pub(crate) fn stream_stdin(
+    raw_format: Option<RawAudioFormat>,
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    config_tx: oneshot::Sender<(u32, u16)>,
+    ready_rx: oneshot::Receiver<()>,
+    fast_mode: bool,
+) -> StreamResult {
+    let stdin = std::io::stdin();
+    match raw_format {
+        Some(format) => stream_raw(stdin.lock(), &format, tx, config_tx, ready_rx, fast_mode),
+        None => decode_media_source(
+            Box::new(ReadOnlySource::new(stdin)),
+            &Hint::new(),
+            tx,
+            config_tx,
+            ready_rx,
+            fast_mode,
+        ),
     }
 }
+
+/// Forward raw audio bytes without decoding. Blocks the calling thread.
+pub(crate) fn stream_raw<R: Read>(
+    mut reader: R,
+    format: &RawAudioFormat,
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    config_tx: oneshot::Sender<(u32, u16)>,
+    ready_rx: oneshot::Receiver<()>,
+    fast_mode: bool,
+) -> StreamResult {
+    let bytes_per_second = bytes_per_sample(&format.encoding)
+        .map(|bytes| bytes * format.sample_rate * format.channels as u32);
+    if bytes_per_second.is_none() && !fast_mode {
+        eprintln!(
+            "Cannot pace {} audio to real time; sending it as fast as it is read",
+            format.encoding
+        );
+    }
+
+    println!(
+        "Audio:   raw {}, {} Hz, {} channel(s)",
+        format.encoding, format.sample_rate, format.channels
+    );
+    let _ = config_tx.send((format.sample_rate, format.channels));
+    let _ = ready_rx.blocking_recv();
+
+    // Read roughly 100ms of audio at a time.
+    let chunk_size = bytes_per_second.map_or(4096, |rate| (rate as usize / 10).max(1));
+    let mut pacer = RealtimePacer::new();
+    loop {
+        let mut chunk = vec![0u8; chunk_size];
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Box::new(e)),
+        };
+        chunk.truncate(read);
+        if tx.send(chunk).is_err() {
+            break;
+        }
+
+        if !fast_mode && let Some(rate) = bytes_per_second {
+            pacer.wait(Duration::from_secs_f64(read as f64 / rate as f64));
+        }
+    }
+
+    Ok(())
+}
+
+/// Probe and decode a non-seekable media source to linear16. Blocks the
+/// calling thread.
+pub(crate) fn decode_media_source(
+    source: Box<dyn MediaSource>,
+    hint: &Hint,
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    config_tx: oneshot::Sender<(u32, u16)>,
+    ready_rx: oneshot::Receiver<()>,
+    fast_mode: bool,
+) -> StreamResult {
+    let (format, track_id, codec_params) = probe_track(source, hint)?;
+    let sample_rate = codec_params.sample_rate.ok_or("Sample rate not found")?;
+    let channels = codec_params.channels.ok_or("Channels not found")?.count() as u16;
+
+    println!("Format:  {}", codec_name(codec_params.codec));
+    println!("Audio:   {sample_rate} Hz, {channels} channel(s)");
+    let _ = config_tx.send((sample_rate, channels));
+    let _ = ready_rx.blocking_recv();
+
+    let mut pacer = RealtimePacer::new();
+    decode_track(format, track_id, &codec_params, false, |audio, frames| {
+        if tx.send(audio).is_err() {
+            return false;
+        }
+        if !fast_mode {
+            pacer.wait(Duration::from_secs_f64(frames as f64 / sample_rate as f64));
+        }
+        true
+    })
+}
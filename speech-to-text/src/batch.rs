@@ -0,0 +1,410 @@
+use clap::Args;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::transcribe::{
+    TranscribeOptions, build_listen_url, output_extension, post_audio, render_output,
+    response_summary,
+};
+
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "aac", "aif", "aiff", "alac", "flac", "m4a", "mkv", "mp3", "mp4", "ogg", "opus", "wav", "webm",
+];
+
+const DEFAULT_MANIFEST_NAME: &str = "dg-stt-manifest.jsonl";
+
+#[derive(Args)]
+pub struct TranscribeBatchArgs {
+    /// Directory to search recursively for audio files, or a glob pattern (e.g. "calls/**/*.wav")
+    #[arg(short, long)]
+    pub input: String,
+
+    /// Number of transcription requests to run concurrently
+    #[arg(long, default_value_t = 4)]
+    pub concurrency: usize,
+
+    /// Directory for transcript outputs; defaults to writing next to each input file
+    #[arg(long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// JSONL manifest of results; defaults to dg-stt-manifest.jsonl in the output or input directory
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+
+    /// Transcribe every file again, even if the manifest records it as finished
+    #[arg(long)]
+    pub force: bool,
+
+    #[command(flatten)]
+    pub options: TranscribeOptions,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ManifestStatus {
+    Ok,
+    Error,
+}
+
+/// One line of the batch manifest. Later lines for the same file supersede
+/// earlier ones, so re-running appends rather than rewriting the manifest.
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    file: PathBuf,
+    status: ManifestStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<f64>,
+    #[serde(default)]
+    elapsed_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+struct BatchJob {
+    file: PathBuf,
+    output: PathBuf,
+}
+
+/// Resolve `--input` into a sorted list of files plus the directory that
+/// relative output paths are computed from.
+fn collect_inputs(
+    input: &str,
+) -> Result<(Vec<PathBuf>, Option<PathBuf>), Box<dyn std::error::Error>> {
+    let input_path = Path::new(input);
+    if input_path.is_dir() {
+        let mut files = Vec::new();
+        walk_audio_files(input_path, &mut files)?;
+        files.sort();
+        return Ok((files, Some(input_path.to_path_buf())));
+    }
+
+    let mut files = Vec::new();
+    for entry in glob::glob(input)? {
+        let path = entry?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok((files, None))
+}
+
+fn walk_audio_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_audio_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Output path for `file`: next to the input, or mirrored under `output_dir`
+/// relative to `base_dir` (falling back to the bare file name for glob inputs).
+fn output_path(
+    file: &Path,
+    base_dir: Option<&Path>,
+    output_dir: Option<&Path>,
+    extension: &str,
+) -> PathBuf {
+    match output_dir {
+        Some(output_dir) => {
+            let relative = base_dir
+                .and_then(|base| file.strip_prefix(base).ok())
+                .map(Path::to_path_buf)
+                .or_else(|| file.file_name().map(PathBuf::from))
+                .unwrap_or_else(|| file.to_path_buf());
+            output_dir.join(relative).with_extension(extension)
+        }
+        None => file.with_extension(extension),
+    }
+}
+
+/// Files whose most recent manifest entry succeeded and whose output still exists.
+fn finished_files(manifest: &Path) -> std::io::Result<HashSet<PathBuf>> {
+    let file = match std::fs::File::open(manifest) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut latest: HashMap<PathBuf, ManifestEntry> = HashMap::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if let Ok(entry) = serde_json::from_str::<ManifestEntry>(&line) {
+            latest.insert(entry.file.clone(), entry);
+        }
+    }
+
+    Ok(latest
+        .into_values()
+        .filter(|entry| {
+            entry.status == ManifestStatus::Ok && entry.output.as_deref().is_some_and(Path::exists)
+        })
+        .map(|entry| entry.file)
+        .collect())
+}
+
+async fn transcribe_file(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<&str>,
+    options: &TranscribeOptions,
+    job: &BatchJob,
+) -> ManifestEntry {
+    let started = Instant::now();
+    let result: Result<(Option<String>, Option<f64>), Box<dyn std::error::Error>> = async {
+        let audio_data = tokio::fs::read(&job.file).await?;
+        let response_text = post_audio(client, url, api_key, audio_data).await?;
+        let summary = response_summary(&response_text);
+        let rendered = render_output(&response_text, options)?;
+
+        if let Some(parent) = job.output.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&job.output, rendered).await?;
+
+        Ok(match summary {
+            Some((request_id, duration)) => (Some(request_id), Some(duration)),
+            None => (None, None),
+        })
+    }
+    .await;
+
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok((request_id, duration)) => ManifestEntry {
+            file: job.file.clone(),
+            status: ManifestStatus::Ok,
+            output: Some(job.output.clone()),
+            request_id,
+            duration,
+            elapsed_ms,
+            error: None,
+        },
+        Err(e) => ManifestEntry {
+            file: job.file.clone(),
+            status: ManifestStatus::Error,
+            output: None,
+            request_id: None,
+            duration: None,
+            elapsed_ms,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+pub async fn run_batch_mode(
+    api_key: Option<String>,
+    args: TranscribeBatchArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.concurrency == 0 {
+        return Err("--concurrency must be at least 1".into());
+    }
+
+    let (files, base_dir) = collect_inputs(&args.input)?;
+    if files.is_empty() {
+        return Err(format!("No audio files found for input: {}", args.input).into());
+    }
+
+    let manifest_path = args.manifest.clone().unwrap_or_else(|| {
+        args.output_dir
+            .clone()
+            .or_else(|| base_dir.clone())
+            .unwrap_or_default()
+            .join(DEFAULT_MANIFEST_NAME)
+    });
+    let finished = if args.force {
+        HashSet::new()
+    } else {
+        finished_files(&manifest_path)?
+    };
+
+    let extension = output_extension(&args.options.output);
+    let mut jobs = Vec::new();
+    let mut outputs = HashSet::new();
+    let mut skipped = 0usize;
+    for file in files {
+        let output = output_path(
+            &file,
+            base_dir.as_deref(),
+            args.output_dir.as_deref(),
+            extension,
+        );
+        if output == file {
+            return Err(format!(
+                "Output for {} would overwrite the input file",
+                file.display()
+            )
+            .into());
+        }
+        if !outputs.insert(output.clone()) {
+            return Err(format!(
+                "Multiple inputs would write to {}; use --output-dir or rename the inputs",
+                output.display()
+            )
+            .into());
+        }
+        if finished.contains(&file) {
+            skipped += 1;
+            continue;
+        }
+        jobs.push(BatchJob { file, output });
+    }
+
+    println!(
+        "Found {} files ({} already finished, {} to transcribe)",
+        jobs.len() + skipped,
+        skipped,
+        jobs.len()
+    );
+    println!("Manifest: {}", manifest_path.display());
+
+    if let Some(parent) = manifest_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut manifest = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&manifest_path)?;
+
+    let client = reqwest::Client::new();
+    let url = build_listen_url(&args.options, None);
+    let total = jobs.len();
+
+    let mut results = futures_util::stream::iter(
+        jobs.iter()
+            .map(|job| transcribe_file(&client, &url, api_key.as_deref(), &args.options, job)),
+    )
+    .buffer_unordered(args.concurrency);
+
+    let mut completed = 0usize;
+    let mut failed = 0usize;
+    while let Some(entry) = results.next().await {
+        completed += 1;
+        match entry.status {
+            ManifestStatus::Ok => println!(
+                "[{}/{}] OK {} -> {} (Request ID: {})",
+                completed,
+                total,
+                entry.file.display(),
+                entry
+                    .output
+                    .as_deref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+                entry.request_id.as_deref().unwrap_or("unknown")
+            ),
+            ManifestStatus::Error => {
+                failed += 1;
+                eprintln!(
+                    "[{}/{}] FAILED {}: {}",
+                    completed,
+                    total,
+                    entry.file.display(),
+                    entry.error.as_deref().unwrap_or("unknown error")
+                );
+            }
+        }
+
+        writeln!(manifest, "{}", serde_json::to_string(&entry)?)?;
+        manifest.flush()?;
+    }
+
+    println!(
+        "\nBatch complete: {} succeeded, {} failed, {} skipped",
+        completed - failed,
+        failed,
+        skipped
+    );
+
+    if failed > 0 {
+        return Err(format!("{failed} of {total} files failed; re-run to retry them").into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ManifestEntry, ManifestStatus, finished_files, output_path};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn outputs_are_written_next_to_inputs_or_mirrored_under_output_dir() {
+        let file = Path::new("calls/2026/a.wav");
+
+        assert_eq!(
+            output_path(file, Some(Path::new("calls")), None, "txt"),
+            PathBuf::from("calls/2026/a.txt")
+        );
+        assert_eq!(
+            output_path(
+                file,
+                Some(Path::new("calls")),
+                Some(Path::new("out")),
+                "srt"
+            ),
+            PathBuf::from("out/2026/a.srt")
+        );
+        assert_eq!(
+            output_path(file, None, Some(Path::new("out")), "json"),
+            PathBuf::from("out/a.json")
+        );
+    }
+
+    #[test]
+    fn manifest_resume_uses_latest_successful_entry() {
+        let dir = std::env::temp_dir().join(format!("dg-stt-batch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("a.txt");
+        std::fs::write(&output, "transcript").unwrap();
+
+        let entry = |file: &str, status, output: Option<PathBuf>| {
+            serde_json::to_string(&ManifestEntry {
+                file: PathBuf::from(file),
+                status,
+                output,
+                request_id: None,
+                duration: None,
+                elapsed_ms: 0,
+                error: None,
+            })
+            .unwrap()
+        };
+        let manifest = dir.join("manifest.jsonl");
+        std::fs::write(
+            &manifest,
+            [
+                entry("a.wav", ManifestStatus::Error, None),
+                entry("a.wav", ManifestStatus::Ok, Some(output.clone())),
+                entry("b.wav", ManifestStatus::Ok, Some(output.clone())),
+                entry("b.wav", ManifestStatus::Error, None),
+                entry("c.wav", ManifestStatus::Ok, Some(dir.join("missing.txt"))),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let finished = finished_files(&manifest).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(finished.len(), 1);
+        assert!(finished.contains(Path::new("a.wav")));
+    }
+}
@@ -0,0 +1,280 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::transcribe::response_summary;
+
+/// Largest callback body accepted. Results for hours of audio with word
+/// timings run to tens of megabytes.
+const MAX_CALLBACK_BODY: usize = 64 * 1024 * 1024;
+
+/// Room for the request line and headers on top of the body.
+const MAX_CALLBACK_HEADERS: usize = 64 * 1024;
+
+/// How long a connection may take to send its whole request.
+const CALLBACK_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+type CallbackError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A minimal local HTTP server that stands in for a callback endpoint and
+/// waits for Deepgram to POST the result of an asynchronous request.
+pub(crate) struct CallbackReceiver {
+    listener: TcpListener,
+}
+
+struct CallbackRequest {
+    method: String,
+    body: Vec<u8>,
+}
+
+impl CallbackReceiver {
+    pub(crate) async fn bind(addr: &str) -> std::io::Result<Self> {
+        Ok(CallbackReceiver {
+            listener: TcpListener::bind(addr).await?,
+        })
+    }
+
+    pub(crate) fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept callback POSTs until one carries `request_id`, returning its body.
+    /// Callbacks for other requests are acknowledged and ignored.
+    pub(crate) async fn wait_for(
+        &self,
+        request_id: &str,
+        timeout: Duration,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        tokio::time::timeout(timeout, self.accept_matching(request_id))
+            .await
+            .map_err(|_| {
+                format!(
+                    "Timed out after {}s waiting for callback for request {request_id}",
+                    timeout.as_secs()
+                )
+            })?
+    }
+
+    /// Each connection is read in its own task, so a peer that connects and
+    /// stays idle cannot hold up the real callback.
+    async fn accept_matching(
+        &self,
+        request_id: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let (body_tx, mut body_rx) = mpsc::channel(8);
+        loop {
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (stream, peer) = accepted?;
+                    let body_tx = body_tx.clone();
+                    tokio::spawn(async move {
+                        if let Some(body) = receive_callback(stream, peer).await {
+                            let _ = body_tx.send((peer, body)).await;
+                        }
+                    });
+                }
+                Some((peer, body)) = body_rx.recv() => {
+                    let Ok(body) = String::from_utf8(body) else {
+                        eprintln!("Ignoring callback from {peer} that is not UTF-8");
+                        continue;
+                    };
+                    match response_summary(&body) {
+                        Some((received_id, _)) if received_id == request_id => return Ok(body),
+                        Some((received_id, _)) => {
+                            eprintln!("Ignoring callback for unexpected request ID {received_id}");
+                        }
+                        None => eprintln!("Ignoring callback from {peer} without a request ID"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Read one request from `stream` and answer it, returning the body of a POST.
+async fn receive_callback(mut stream: TcpStream, peer: SocketAddr) -> Option<Vec<u8>> {
+    let (reader, mut writer) = stream.split();
+    let request = match tokio::time::timeout(CALLBACK_READ_TIMEOUT, read_request(reader)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => {
+            eprintln!("Ignoring malformed callback request from {peer}: {e}");
+            let _ = writer.write_all(&response_bytes(400, "Bad Request")).await;
+            return None;
+        }
+        Err(_) => {
+            eprintln!(
+                "Closing callback connection from {peer} after {}s without a complete request",
+                CALLBACK_READ_TIMEOUT.as_secs()
+            );
+            let _ = writer
+                .write_all(&response_bytes(408, "Request Timeout"))
+                .await;
+            return None;
+        }
+    };
+
+    if request.method != "POST" {
+        let _ = writer
+            .write_all(&response_bytes(405, "Method Not Allowed"))
+            .await;
+        return None;
+    }
+
+    let _ = writer.write_all(&response_bytes(200, "OK")).await;
+    let _ = writer.shutdown().await;
+    Some(request.body)
+}
+
+fn response_bytes(status: u16, reason: &str) -> Vec<u8> {
+    format!("HTTP/1.1 {status} {reason}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+        .into_bytes()
+}
+
+/// Read an HTTP request, refusing bodies over `MAX_CALLBACK_BODY` before
+/// allocating them.
+async fn read_request<R: AsyncRead + Unpin>(reader: R) -> Result<CallbackRequest, CallbackError> {
+    let mut reader = BufReader::new(reader.take((MAX_CALLBACK_BODY + MAX_CALLBACK_HEADERS) as u64));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let method = request_line
+        .split_whitespace()
+        .next()
+        .ok_or("Empty HTTP request")?
+        .to_string();
+
+    let mut content_length = None;
+    let mut chunked = false;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err("Connection closed before end of headers".into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(value.parse::<usize>()?);
+            } else if name.eq_ignore_ascii_case("transfer-encoding")
+                && value.eq_ignore_ascii_case("chunked")
+            {
+                chunked = true;
+            }
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let mut size_line = String::new();
+            reader.read_line(&mut size_line).await?;
+            let size_str = size_line.trim().split(';').next().unwrap_or("");
+            let size = usize::from_str_radix(size_str, 16)?;
+            if size == 0 {
+                break;
+            }
+            if size > MAX_CALLBACK_BODY - body.len() {
+                return Err(too_large());
+            }
+            let start = body.len();
+            body.resize(start + size, 0);
+            reader.read_exact(&mut body[start..]).await?;
+            let mut crlf = [0u8; 2];
+            reader.read_exact(&mut crlf).await?;
+        }
+    } else if let Some(length) = content_length {
+        if length > MAX_CALLBACK_BODY {
+            return Err(too_large());
+        }
+        body.resize(length, 0);
+        reader.read_exact(&mut body).await?;
+    }
+
+    Ok(CallbackRequest { method, body })
+}
+
+fn too_large() -> CallbackError {
+    format!("Body exceeds {} MiB", MAX_CALLBACK_BODY / (1024 * 1024)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallbackReceiver, MAX_CALLBACK_BODY, read_request};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[tokio::test]
+    async fn reads_content_length_and_chunked_bodies() {
+        let request = read_request(
+            &b"POST /callback HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\n\r\nhello"[..],
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.body, b"hello");
+
+        let request = read_request(
+            &b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n"[..],
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.body, b"hello");
+
+        let huge = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_CALLBACK_BODY + 1
+        );
+        let error = read_request(huge.as_bytes()).await.err().unwrap();
+        assert!(error.to_string().contains("exceeds"), "{error}");
+        let error =
+            read_request(&b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nffffffff\r\n"[..])
+                .await
+                .err()
+                .unwrap();
+        assert!(error.to_string().contains("exceeds"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn waits_for_callback_with_matching_request_id() {
+        let receiver = CallbackReceiver::bind("127.0.0.1:0").await.unwrap();
+        let addr = receiver.local_addr().unwrap();
+
+        // A peer that connects and never sends anything must not block the
+        // callbacks behind it.
+        let _idle = TcpStream::connect(addr).await.unwrap();
+        let sender = tokio::spawn(async move {
+            for request_id in ["other", "expected"] {
+                let body = format!(r#"{{"metadata":{{"request_id":"{request_id}"}}}}"#);
+                let mut stream = TcpStream::connect(addr).await.unwrap();
+                stream
+                    .write_all(
+                        format!(
+                            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        )
+                        .as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                assert!(response.starts_with("HTTP/1.1 200"));
+            }
+        });
+
+        let body = receiver
+            .wait_for("expected", Duration::from_secs(5))
+            .await
+            .unwrap();
+        sender.await.unwrap();
+
+        assert!(body.contains(r#""request_id":"expected""#));
+    }
+}
@@ -1,5 +1,9 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
+use crate::batch::TranscribeBatchArgs;
+use crate::eval::EvalArgs;
+use crate::profile::ProfileCommand;
 use crate::stream::StreamSource;
 use crate::transcribe::TranscribeArgs;
 
@@ -9,6 +13,14 @@ use crate::transcribe::TranscribeArgs;
 pub(crate) struct Cli {
     #[command(subcommand)]
     pub(crate) command: Commands,
+
+    /// Apply the named option profile; flags given on the command line take precedence
+    #[arg(long, global = true)]
+    pub(crate) profile: Option<String>,
+
+    /// Profiles file to read instead of ~/.config/deepgram/dg-stt.toml
+    #[arg(long, global = true)]
+    pub(crate) profile_file: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -23,6 +35,27 @@ pub(crate) enum Commands {
         #[command(flatten)]
         args: TranscribeArgs,
     },
+    /// Transcribe every audio file in a directory or glob using the HTTP API
+    TranscribeBatch {
+        #[command(flatten)]
+        args: TranscribeBatchArgs,
+    },
+    /// Score transcription accuracy (WER/CER) against reference transcripts
+    Eval {
+        #[command(flatten)]
+        args: EvalArgs,
+    },
+    /// Inspect option profiles
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    /// List audio hosts and input devices with their supported configurations
+    Devices {
+        /// Only list devices on this audio host (e.g. alsa, jack, wasapi)
+        #[arg(long)]
+        host: Option<String>,
+    },
     /// List available speech-to-text models
     ListModels {
         /// Include non-latest model versions
@@ -0,0 +1,67 @@
+use std::io::{BufRead, IsTerminal};
+use tokio::sync::mpsc;
+
+/// A control message typed on the keyboard during a live stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StreamControl {
+    /// Send `Finalize` so Deepgram flushes its buffered audio as a final result.
+    Finalize,
+    /// Stop or resume sending audio. Keep-alives continue while paused.
+    TogglePause,
+    /// Send `CloseStream` and finish the connection.
+    Close,
+}
+
+pub(crate) const CONTROLS_HELP: &str =
+    "Controls: f = Finalize, p = pause/resume audio, q = CloseStream (press Enter after each key)";
+
+pub(crate) fn parse_control(line: &str) -> Option<StreamControl> {
+    match line.trim().to_ascii_lowercase().as_str() {
+        "f" | "finalize" => Some(StreamControl::Finalize),
+        "p" | "pause" => Some(StreamControl::TogglePause),
+        "q" | "quit" | "close" => Some(StreamControl::Close),
+        _ => None,
+    }
+}
+
+/// Read control keys from stdin and forward them to every connection. Returns
+/// `false` without starting if stdin is not a terminal.
+///
+/// Stdin is read on a plain thread rather than a Tokio blocking task, since a
+/// pending blocking read would otherwise hold up runtime shutdown.
+pub(crate) fn spawn_keyboard_controls(senders: Vec<mpsc::UnboundedSender<StreamControl>>) -> bool {
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match parse_control(&line) {
+                Some(control) => {
+                    for sender in &senders {
+                        let _ = sender.send(control);
+                    }
+                }
+                None if line.trim().is_empty() => {}
+                None => eprintln!("Unknown control {:?}. {}", line.trim(), CONTROLS_HELP),
+            }
+        }
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamControl, parse_control};
+
+    #[test]
+    fn parses_control_keys() {
+        assert_eq!(parse_control("f\n"), Some(StreamControl::Finalize));
+        assert_eq!(parse_control(" P "), Some(StreamControl::TogglePause));
+        assert_eq!(parse_control("q"), Some(StreamControl::Close));
+        assert_eq!(parse_control("x"), None);
+    }
+}
@@ -1,12 +1,46 @@
 use futures_util::{SinkExt, StreamExt};
+use std::collections::VecDeque;
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::sync::{mpsc, oneshot};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async, tungstenite::protocol::Message,
+};
 
-use crate::audio::connection_prefix;
-use crate::protocol::{Channel, DeepgramClientConfig, DeepgramResponse, StreamResult};
+use crate::controls::StreamControl;
+use crate::encode::encoded_bytes_per_second;
+use crate::latency::LatencyTracker;
+use crate::protocol::{
+    Channel, DeepgramClientConfig, DeepgramResponse, StreamError, StreamResult, Word,
+};
+use crate::query::{print_dry_run, push_extra_params, redacted_authorization};
+use crate::serve::{ServeEvent, TranscriptServer};
+use crate::subtitles::{CueWord, SubtitleFormat, SubtitleWriter, connection_subtitle_path};
+use crate::transcript_log::{TranscriptLog, TranscriptLogEntry};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Model requested for `--language multi` when no `--model` is given.
+const MULTILINGUAL_MODEL: &str = "nova-3";
+
+/// How long a session waits without a message from Deepgram before it
+/// finishes. Suspended while audio is paused, since Deepgram has nothing to
+/// send until audio resumes.
+const RESPONSE_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The channels connecting one streaming connection to its audio source and
+/// the rest of the CLI.
+pub(crate) struct ClientInputs {
+    pub(crate) audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    /// Signalled once the connection can take audio, for sources that wait.
+    pub(crate) ready_tx: Option<oneshot::Sender<()>>,
+    pub(crate) shutdown_rx: mpsc::Receiver<()>,
+    /// Keyboard controls, for sources that accept them.
+    pub(crate) control_rx: Option<mpsc::UnboundedReceiver<StreamControl>>,
+}
 
 pub(crate) async fn run_deepgram_client(
     config: DeepgramClientConfig,
@@ -14,17 +48,221 @@ pub(crate) async fn run_deepgram_client(
     connection_count: usize,
     detected_sample_rate: u32,
     detected_channels: u16,
-    mut audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
-    ready_tx: Option<oneshot::Sender<()>>,
-    mut shutdown_rx: mpsc::Receiver<()>,
+    inputs: ClientInputs,
 ) -> StreamResult {
-    let prefix = connection_prefix(connection_id, connection_count);
+    let ClientInputs {
+        mut audio_rx,
+        mut ready_tx,
+        mut shutdown_rx,
+        control_rx,
+    } = inputs;
+    let prefix = config.connection_prefix(connection_id, connection_count);
 
     // UtteranceEnd relies on interim results to detect the gap after the last finalized word.
     if config.utterance_end.is_some() && !config.interim_results {
         return Err("--utterance-end requires --interim-results".into());
     }
 
+    let subtitle_writer = match &config.subtitles {
+        Some(path) => {
+            let format = SubtitleFormat::from_path(path)
+                .ok_or("--subtitles path must end in .srt or .vtt")?;
+            let path = connection_subtitle_path(
+                path,
+                connection_id,
+                connection_count,
+                config.channel_label(connection_id),
+            );
+            println!("{prefix}Writing subtitles to {}", path.display());
+            Some(SubtitleWriter::create(
+                &path,
+                format,
+                config.subtitle_options.clone(),
+            )?)
+        }
+        None => None,
+    };
+
+    let transcript_log = match &config.transcript_log {
+        Some(path) => Some(
+            TranscriptLog::open(path)
+                .map_err(|e| format!("Failed to open transcript log {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+
+    let url = build_stream_url(&config, detected_sample_rate, detected_channels);
+
+    let delay = ramp_up_delay(config.ramp_up, connection_id, connection_count);
+    if !delay.is_zero() {
+        // A delayed connection joins the shared audio mid-stream, so it doesn't
+        // hold the source back and discards the audio sent before it starts.
+        if let Some(tx) = ready_tx.take() {
+            let _ = tx.send(());
+        }
+        println!("{prefix}Starting in {:.1}s", delay.as_secs_f64());
+        let start = tokio::time::sleep(delay);
+        tokio::pin!(start);
+        loop {
+            tokio::select! {
+                _ = &mut start => break,
+                audio = audio_rx.recv() => if audio.is_none() {
+                    return Ok(());
+                },
+                _ = shutdown_rx.recv() => return Ok(()),
+            }
+        }
+    }
+
+    println!("{prefix}Connecting to Deepgram WebSocket...");
+    let (mut ws_stream, request_id) = connect_websocket(&config, &url, &prefix).await?;
+    record_request_id(&config, connection_id, request_id.as_deref());
+
+    // Signal that we're ready to receive audio
+    if let Some(tx) = ready_tx {
+        let _ = tx.send(());
+    }
+
+    let bytes_per_second = encoded_bytes_per_second(
+        config.encoding.as_deref().unwrap_or("linear16"),
+        config.sample_rate_override.unwrap_or(detected_sample_rate),
+        config.channels_override.unwrap_or(detected_channels),
+    );
+    let replay_capacity = if config.reconnect.reconnect {
+        (bytes_per_second * config.reconnect.replay_seconds.max(0.0)) as usize
+    } else {
+        0
+    };
+    let mut replay = ReplayBuffer::new(replay_capacity);
+
+    let state = Arc::new(Mutex::new(ResponseState {
+        prefix: prefix.clone(),
+        silent: config.silent,
+        diarize: config.diarize,
+        subtitle_writer,
+        transcript_log,
+        connection_id,
+        channel_label: config.channel_label(connection_id).map(str::to_string),
+        server: config.server.clone(),
+        request_id,
+        time_offset: 0.0,
+        last_final_end: 0.0,
+        skip_until: None,
+        latency: config
+            .latency
+            .is_some()
+            .then(|| LatencyTracker::new(connection_id)),
+        bytes_per_second,
+        audio_sent_seconds: 0.0,
+        finalized_since_audio: false,
+    }));
+
+    let mut inputs = StreamInputs {
+        audio_rx,
+        shutdown_rx,
+        control_rx,
+        paused: false,
+    };
+    let mut audio_count = 0;
+    let result = loop {
+        let session_end = run_session(
+            ws_stream,
+            &prefix,
+            &mut inputs,
+            &mut replay,
+            state.clone(),
+            &mut audio_count,
+            RESPONSE_IDLE_TIMEOUT,
+        )
+        .await;
+
+        if session_end == SessionEnd::Finished || !config.reconnect.reconnect {
+            break Ok(());
+        }
+
+        println!(
+            "\r\x1b[2K{prefix}Connection lost after {} audio chunks, reconnecting...",
+            audio_count
+        );
+        let request_id = match reconnect(&config, &url, &prefix, &mut inputs.shutdown_rx).await {
+            Ok(Some((stream, request_id))) => {
+                ws_stream = stream;
+                record_request_id(&config, connection_id, request_id.as_deref());
+                request_id
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        };
+
+        // The new session's timestamps start at the first replayed byte.
+        let offset = replay.start_bytes() as f64 / bytes_per_second;
+        {
+            let mut state = state.lock().unwrap();
+            state.request_id = request_id;
+            state.time_offset = offset;
+            state.skip_until = Some(state.last_final_end);
+        }
+        println!(
+            "{prefix}Replaying {:.1}s of buffered audio from {:.2}s",
+            replay.buffered_bytes() as f64 / bytes_per_second,
+            offset
+        );
+    };
+
+    // Keep the latency collected so far even when reconnecting failed.
+    if let (Some(collector), Some(tracker)) =
+        (&config.latency, state.lock().unwrap().latency.take())
+    {
+        collector.push(tracker);
+    }
+
+    result
+}
+
+/// Add a connection's request ID to the `--record` sidecar.
+fn record_request_id(
+    config: &DeepgramClientConfig,
+    connection_id: usize,
+    request_id: Option<&str>,
+) {
+    if let (Some(recording), Some(request_id)) = (&config.recording, request_id)
+        && let Err(e) = recording.add_request_id(
+            connection_id,
+            config.channel_label(connection_id),
+            request_id,
+        )
+    {
+        eprintln!("Failed to update the recording sidecar: {}", e);
+    }
+}
+
+/// How long connection `connection_id` waits before connecting, spreading
+/// `connection_count` starts evenly so the last one opens `ramp_up` seconds in.
+fn ramp_up_delay(ramp_up: Option<f64>, connection_id: usize, connection_count: usize) -> Duration {
+    match ramp_up {
+        Some(ramp_up) if ramp_up > 0.0 && connection_count > 1 => Duration::from_secs_f64(
+            ramp_up * (connection_id - 1) as f64 / (connection_count - 1) as f64,
+        ),
+        _ => Duration::ZERO,
+    }
+}
+
+/// HTTP status of a rejected WebSocket upgrade, if that is why `error` occurred.
+pub(crate) fn handshake_status(error: &StreamError) -> Option<u16> {
+    match error.downcast_ref::<tokio_tungstenite::tungstenite::Error>() {
+        Some(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            Some(response.status().as_u16())
+        }
+        _ => None,
+    }
+}
+
+/// Build the streaming `/v1/listen` URL for the requested options.
+pub(crate) fn build_stream_url(
+    config: &DeepgramClientConfig,
+    detected_sample_rate: u32,
+    detected_channels: u16,
+) -> String {
     // Use custom endpoint or default to Deepgram API
     let base_url = config
         .endpoint
@@ -95,8 +333,13 @@ pub(crate) async fn run_deepgram_client(
         params.push("topics=true".to_string());
     }
 
-    // Add model parameter if specified
-    if let Some(model_name) = &config.model {
+    // Add model parameter if specified. Code-switching with language=multi
+    // needs a multilingual model, which the API default is not.
+    let model = config
+        .model
+        .as_deref()
+        .or_else(|| (config.language.as_deref() == Some("multi")).then_some(MULTILINGUAL_MODEL));
+    if let Some(model_name) = model {
         params.push(format!("model={}", model_name));
     }
 
@@ -144,6 +387,8 @@ pub(crate) async fn run_deepgram_client(
         }
     }
 
+    push_extra_params(&mut params, &config.extra_params);
+
     // Join all parameters
     url.push_str(&params.join("&"));
 
@@ -155,16 +400,50 @@ pub(crate) async fn run_deepgram_client(
         ));
     }
 
-    println!("{prefix}Connecting to Deepgram WebSocket...");
+    url
+}
 
-    let url_parsed = url::Url::parse(&url)?;
+/// Print the handshake `connect_websocket` would send, for `--dry-run`.
+pub(crate) fn print_stream_dry_run(
+    config: &DeepgramClientConfig,
+    detected_sample_rate: u32,
+    detected_channels: u16,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let url = build_stream_url(config, detected_sample_rate, detected_channels);
+    let host = url::Url::parse(&url)?
+        .host_str()
+        .ok_or("Invalid host in URL")?
+        .to_string();
+    let mut headers = vec![
+        ("Host", host),
+        ("Upgrade", "websocket".to_string()),
+        ("Connection", "Upgrade".to_string()),
+        (
+            "Sec-WebSocket-Key",
+            "(generated per connection)".to_string(),
+        ),
+        ("Sec-WebSocket-Version", "13".to_string()),
+    ];
+    if let Some(api_key) = &config.api_key {
+        headers.push(("Authorization", redacted_authorization(api_key)));
+    }
+    print_dry_run("GET", &url, &headers);
+    Ok(())
+}
+
+async fn connect_websocket(
+    config: &DeepgramClientConfig,
+    url: &str,
+    prefix: &str,
+) -> Result<(WsStream, Option<String>), StreamError> {
+    let url_parsed = url::Url::parse(url)?;
     let host = url_parsed.host_str().ok_or("Invalid host in URL")?;
 
-    println!("{prefix}Connecting to Deepgram URL: {0}", &url);
+    println!("{prefix}Connecting to Deepgram URL: {0}", url);
 
     let mut request_builder = tokio_tungstenite::tungstenite::http::Request::builder()
         .method("GET")
-        .uri(&url)
+        .uri(url)
         .header("Host", host)
         .header("Upgrade", "websocket")
         .header("Connection", "Upgrade")
@@ -198,19 +477,93 @@ pub(crate) async fn run_deepgram_client(
         e
     })?;
     println!("{prefix}Connected to Deepgram!");
-    if let Some(request_id) = response.headers().get("dg-request-id") {
+    let request_id = response
+        .headers()
+        .get("dg-request-id")
+        .map(|request_id| request_id.to_str().unwrap_or("(invalid)").to_string());
+    if let Some(request_id) = &request_id {
+        println!("{prefix}Request ID: {}", request_id);
+    }
+
+    Ok((ws_stream, request_id))
+}
+
+/// Retry the WebSocket handshake with exponential backoff, returning the new
+/// stream and its request ID. Returns `None` if a shutdown signal arrives while
+/// waiting.
+async fn reconnect(
+    config: &DeepgramClientConfig,
+    url: &str,
+    prefix: &str,
+    shutdown_rx: &mut mpsc::Receiver<()>,
+) -> Result<Option<(WsStream, Option<String>)>, StreamError> {
+    let mut backoff = Duration::from_millis(config.reconnect.reconnect_backoff);
+    for attempt in 1..=config.reconnect.reconnect_attempts {
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = shutdown_rx.recv() => return Ok(None),
+        }
+
         println!(
-            "{prefix}Request ID: {}",
-            request_id.to_str().unwrap_or("(invalid)")
+            "{prefix}Reconnect attempt {}/{}...",
+            attempt, config.reconnect.reconnect_attempts
         );
+        match connect_websocket(config, url, prefix).await {
+            Ok(connection) => return Ok(Some(connection)),
+            Err(e) => eprintln!("{prefix}Reconnect attempt {} failed: {}", attempt, e),
+        }
+        backoff = backoff.saturating_mul(2);
     }
 
-    // Signal that we're ready to receive audio
-    if let Some(tx) = ready_tx {
-        let _ = tx.send(());
+    Err(format!(
+        "Failed to reconnect after {} attempts",
+        config.reconnect.reconnect_attempts
+    )
+    .into())
+}
+
+/// The receivers feeding one connection, kept across reconnects.
+struct StreamInputs {
+    audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    shutdown_rx: mpsc::Receiver<()>,
+    control_rx: Option<mpsc::UnboundedReceiver<StreamControl>>,
+    /// Audio received while paused is discarded instead of sent.
+    paused: bool,
+}
+
+async fn next_control(
+    control_rx: &mut Option<mpsc::UnboundedReceiver<StreamControl>>,
+) -> Option<StreamControl> {
+    match control_rx {
+        Some(control_rx) => control_rx.recv().await,
+        None => std::future::pending().await,
     }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SessionEnd {
+    /// The audio source finished or a shutdown or close was requested, and CloseStream was sent.
+    Finished,
+    /// The connection ended while audio was still being streamed.
+    Dropped,
+}
 
+/// Stream audio over one WebSocket connection until the source finishes, a
+/// shutdown is requested, or the connection drops. Audio still held in
+/// `replay` is sent first so a reconnected session picks up where the last one
+/// may have lost audio. The session also ends after `idle_timeout` without a
+/// message from Deepgram, except while audio is paused.
+async fn run_session(
+    ws_stream: WsStream,
+    prefix: &str,
+    inputs: &mut StreamInputs,
+    replay: &mut ReplayBuffer,
+    state: Arc<Mutex<ResponseState>>,
+    audio_count: &mut usize,
+    idle_timeout: Duration,
+) -> SessionEnd {
     let (ws_sender, mut ws_receiver) = ws_stream.split();
+    let (paused_tx, mut paused_rx) = watch::channel(inputs.paused);
 
     let (result_tx, mut result_rx) = mpsc::unbounded_channel::<()>();
     let (msg_tx, mut msg_rx) = mpsc::unbounded_channel::<Message>();
@@ -243,110 +596,113 @@ pub(crate) async fn run_deepgram_client(
         }
     });
 
-    let response_prefix = prefix.clone();
-    let silent = config.silent;
-    let diarize = config.diarize;
+    let response_prefix = prefix.to_string();
+    let sender_state = state.clone();
     let response_handler = tokio::spawn(async move {
         let mut last_message_time = tokio::time::Instant::now();
-        let timeout_duration = Duration::from_secs(10);
+        let silent = state.lock().unwrap().silent;
+        // False if the socket failed rather than being closed by the server.
+        let mut closed_cleanly = true;
 
         loop {
+            let paused = *paused_rx.borrow();
             tokio::select! {
+                Ok(()) = paused_rx.changed() => {
+                    // Restart the idle timeout from the pause or resume.
+                    last_message_time = tokio::time::Instant::now();
+                }
                 msg = ws_receiver.next() => {
                     match msg {
                         Some(Ok(Message::Text(text))) => {
                             last_message_time = tokio::time::Instant::now();
-                            match serde_json::from_str::<DeepgramResponse>(&text) {
-                                Ok(response) => {
-                                    if response.message_type == "Metadata" {
-                                        if !silent {
-                                            println!("{}Metadata: {}", response_prefix, text);
-                                        }
-                                    } else if response.message_type == "Results" {
-                                        let channel = response.channel.and_then(|channel| {
-                                            serde_json::from_value::<Channel>(channel).ok()
-                                        });
-                                        if let Some(channel) = channel {
-                                            for alternative in channel.alternatives {
-                                                if !alternative.transcript.trim().is_empty() && !silent {
-                                                    if diarize && !alternative.words.is_empty() {
-                                                        // Group consecutive words by speaker
-                                                        let mut segments: Vec<(u32, Vec<&str>)> = Vec::new();
-                                                        for word in &alternative.words {
-                                                            let speaker = word.speaker.unwrap_or(0);
-                                                            if let Some(last) = segments.last_mut() {
-                                                                if last.0 == speaker {
-                                                                    last.1.push(&word.word);
-                                                                    continue;
-                                                                }
-                                                            }
-                                                            segments.push((speaker, vec![&word.word]));
-                                                        }
-                                                        for (speaker, words) in &segments {
-                                                            println!("\r\x1b[2K{}Speaker {}: {}", response_prefix, speaker, words.join(" "));
-                                                        }
-                                                    } else {
-                                                        print!("\r\x1b[2K{}Transcript: {}", response_prefix, alternative.transcript);
-                                                        if let Some(confidence) = alternative.confidence {
-                                                            print!(" (Confidence: {:.1}%)", confidence * 100.0);
-                                                        }
-                                                        println!();
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    eprintln!("Failed to parse response: {}", e);
-                                    if let Ok(mut f) = OpenOptions::new()
-                                        .create(true)
-                                        .append(true)
-                                        .open("dg-stt-debug.log")
-                                    {
-                                        let _ = writeln!(f, "--- parse error: {} ---", e);
-                                        let _ = writeln!(f, "{}", text);
-                                    }
-                                }
-                            }
+                            state.lock().unwrap().handle_message(&text);
                         }
                         Some(Ok(Message::Close(_))) => {
                             if !silent {
-                                println!("{}WebSocket connection closed by server", response_prefix);
+                                println!("\r\x1b[2K{}WebSocket connection closed by server", response_prefix);
                             }
                             break;
                         }
                         Some(Err(e)) => {
                             eprintln!("{}WebSocket error: {}", response_prefix, e);
+                            closed_cleanly = false;
+                            break;
+                        }
+                        None => {
+                            closed_cleanly = false;
                             break;
                         }
-                        None => break,
                         _ => {}
                     }
                 }
-                _ = tokio::time::sleep_until(last_message_time + timeout_duration) => {
+                _ = tokio::time::sleep_until(last_message_time + idle_timeout), if !paused => {
                     // No messages received for timeout duration, we're done
                     if !silent {
-                        println!("{}No more messages received, finishing...", response_prefix);
+                        println!("\r\x1b[2K{}No more messages received, finishing...", response_prefix);
                     }
                     break;
                 }
             }
         }
         let _ = result_tx.send(());
+        closed_cleanly
     });
 
-    let mut audio_count = 0;
+    for audio_data in replay.chunks() {
+        let _ = msg_tx.send(Message::Binary(audio_data.clone().into()));
+    }
+
+    let mut session_end = SessionEnd::Finished;
     loop {
         tokio::select! {
-            Some(audio_data) = audio_rx.recv() => {
-                audio_count += 1;
+            audio = inputs.audio_rx.recv() => {
+                let Some(audio_data) = audio else {
+                    // Audio source exhausted (file done) — tell Deepgram we're finished
+                    let close_stream_msg = serde_json::json!({"type": "CloseStream"});
+                    if let Ok(msg_str) = serde_json::to_string(&close_stream_msg) {
+                        let _ = msg_tx.send(Message::Text(msg_str.into()));
+                    }
+                    break;
+                };
+                if inputs.paused {
+                    continue;
+                }
+                *audio_count += 1;
+                replay.push(&audio_data);
+                sender_state.lock().unwrap().audio_sent(replay.total_bytes());
                 if msg_tx.send(Message::Binary(audio_data.into())).is_err() {
                     eprintln!("{prefix}Failed to send audio to WebSocket");
+                    session_end = SessionEnd::Dropped;
                     break;
                 }
             }
-            _ = shutdown_rx.recv() => {
+            Some(control) = next_control(&mut inputs.control_rx) => match control {
+                StreamControl::Finalize => {
+                    println!("\r\x1b[2K{prefix}Sending Finalize message...");
+                    let finalize_msg = serde_json::json!({"type": "Finalize"});
+                    if let Ok(msg_str) = serde_json::to_string(&finalize_msg) {
+                        let _ = msg_tx.send(Message::Text(msg_str.into()));
+                    }
+                }
+                StreamControl::TogglePause => {
+                    inputs.paused = !inputs.paused;
+                    let _ = paused_tx.send(inputs.paused);
+                    if inputs.paused {
+                        println!("\r\x1b[2K{prefix}Audio paused; keep-alives continue");
+                    } else {
+                        println!("\r\x1b[2K{prefix}Audio resumed");
+                    }
+                }
+                StreamControl::Close => {
+                    println!("\r\x1b[2K{prefix}Sending CloseStream message...");
+                    let close_stream_msg = serde_json::json!({"type": "CloseStream"});
+                    if let Ok(msg_str) = serde_json::to_string(&close_stream_msg) {
+                        let _ = msg_tx.send(Message::Text(msg_str.into()));
+                    }
+                    break;
+                }
+            },
+            _ = inputs.shutdown_rx.recv() => {
                 println!("\n{prefix}Received shutdown signal, sending CloseStream message...");
                 // Send CloseStream message
                 let close_stream_msg = serde_json::json!({"type": "CloseStream"});
@@ -357,23 +713,18 @@ pub(crate) async fn run_deepgram_client(
             }
             _ = result_rx.recv() => {
                 // WebSocket connection was closed.
-                break;
-            }
-            else => {
-                // Audio source exhausted (file done) — tell Deepgram we're finished
-                let close_stream_msg = serde_json::json!({"type": "CloseStream"});
-                if let Ok(msg_str) = serde_json::to_string(&close_stream_msg) {
-                    let _ = msg_tx.send(Message::Text(msg_str.into()));
-                }
+                session_end = SessionEnd::Dropped;
                 break;
             }
         }
     }
 
-    println!(
-        "{prefix}Sent {} audio chunks, waiting for transcription results...",
-        audio_count
-    );
+    if session_end == SessionEnd::Finished {
+        println!(
+            "{prefix}Sent {} audio chunks, waiting for transcription results...",
+            audio_count
+        );
+    }
 
     // Stop sending messages
     drop(msg_tx);
@@ -381,7 +732,20 @@ pub(crate) async fn run_deepgram_client(
     // Wait for the response handler first — it completes as soon as the WS closes.
     // Awaiting keepalive/sender first would hang: they can only exit after ws_sender
     // errors, which doesn't happen until the TCP teardown completes (several seconds).
-    let _ = response_handler.await;
+    // A dropped connection may leave the receiver waiting on a dead socket, so abort it.
+    // If the socket fails while results are still pending, the tail of the audio
+    // may never be transcribed, so treat it as a drop as well. A server that
+    // drops the socket after CloseStream once the last audio was finalized has
+    // sent everything, and reconnecting would only transcribe it again.
+    if session_end == SessionEnd::Dropped {
+        response_handler.abort();
+    }
+    if let Ok(false) = response_handler.await
+        && !(session_end == SessionEnd::Finished
+            && sender_state.lock().unwrap().finalized_since_audio)
+    {
+        session_end = SessionEnd::Dropped;
+    }
 
     // WS is now closed; abort the other tasks rather than waiting for the chain to
     // propagate through sender_task → keepalive_task.
@@ -390,7 +754,382 @@ pub(crate) async fn run_deepgram_client(
     let _ = keepalive_task.await;
     let _ = sender_task.await;
 
-    Ok(())
+    session_end
+}
+
+/// Transcript handling state that outlives a single WebSocket session, so a
+/// reconnected stream keeps writing to the same outputs on the same timeline.
+struct ResponseState {
+    prefix: String,
+    silent: bool,
+    diarize: bool,
+    subtitle_writer: Option<SubtitleWriter>,
+    transcript_log: Option<TranscriptLog>,
+    connection_id: usize,
+    channel_label: Option<String>,
+    server: Option<TranscriptServer>,
+    /// Request ID of the current session.
+    request_id: Option<String>,
+    /// Seconds added to every timestamp of the current session.
+    time_offset: f64,
+    /// End of the latest finalized result, on the rebased timeline.
+    last_final_end: f64,
+    /// After a reconnect, results covering replayed audio that was already
+    /// finalized by the previous session are dropped.
+    skip_until: Option<f64>,
+    latency: Option<LatencyTracker>,
+    bytes_per_second: f64,
+    /// Position in the overall stream of the newest audio sent, in seconds.
+    audio_sent_seconds: f64,
+    /// Whether a final result has arrived since audio was last sent, so the
+    /// audio sent so far has been transcribed.
+    finalized_since_audio: bool,
+}
+
+impl ResponseState {
+    fn audio_sent(&mut self, total_bytes: u64) {
+        self.audio_sent_seconds = total_bytes as f64 / self.bytes_per_second;
+        self.finalized_since_audio = false;
+        if let Some(latency) = self.latency.as_mut() {
+            latency.audio_sent();
+        }
+    }
+
+    fn handle_message(&mut self, text: &str) {
+        let response_prefix = self.prefix.as_str();
+        let silent = self.silent;
+        let diarize = self.diarize;
+        match serde_json::from_str::<DeepgramResponse>(text) {
+            Ok(mut response) => {
+                if response.message_type == "Metadata" {
+                    if !silent {
+                        println!("{}Metadata: {}", response_prefix, text);
+                    }
+                } else if response.message_type == "Results" {
+                    let channel = response
+                        .channel
+                        .take()
+                        .and_then(|channel| serde_json::from_value::<Channel>(channel).ok());
+                    if let Some(mut channel) = channel {
+                        rebase_results(&mut response, &mut channel, self.time_offset);
+                        if let Some(skip_until) = self.skip_until
+                            && !skip_finalized_words(&mut response, &mut channel, skip_until)
+                        {
+                            return;
+                        }
+                        if let Some(latency) = self.latency.as_mut() {
+                            latency.result_received(
+                                channel
+                                    .alternatives
+                                    .iter()
+                                    .any(|alternative| !alternative.transcript.trim().is_empty()),
+                                response.is_final,
+                                response.start + response.duration,
+                                self.audio_sent_seconds,
+                            );
+                        }
+                        if let (Some(server), Some(alternative)) =
+                            (&self.server, channel.alternatives.first())
+                        {
+                            server.publish(&ServeEvent::Transcript {
+                                connection: self.connection_id,
+                                channel_label: self.channel_label.as_deref(),
+                                request_id: self.request_id.as_deref(),
+                                is_final: response.is_final,
+                                speech_final: response.speech_final,
+                                start: response.start,
+                                end: response.start + response.duration,
+                                transcript: &alternative.transcript,
+                                confidence: alternative.confidence,
+                                speaker: alternative.words.first().and_then(|word| word.speaker),
+                                words: &alternative.words,
+                            });
+                        }
+                        if response.is_final {
+                            self.last_final_end =
+                                self.last_final_end.max(response.start + response.duration);
+                            self.finalized_since_audio = true;
+                        }
+
+                        if response.is_final
+                            && let (Some(log), Some(alternative)) =
+                                (self.transcript_log.as_mut(), channel.alternatives.first())
+                            && let Err(e) = log.write(&TranscriptLogEntry {
+                                connection: self.connection_id,
+                                channel_label: self.channel_label.as_deref(),
+                                request_id: self.request_id.as_deref(),
+                                start: response.start,
+                                duration: response.duration,
+                                is_final: response.is_final,
+                                speech_final: response.speech_final,
+                                from_finalize: response.from_finalize,
+                                channel_index: &response.channel_index,
+                                transcript: &alternative.transcript,
+                                confidence: alternative.confidence,
+                                words: &alternative.words,
+                            })
+                        {
+                            eprintln!("{}Failed to write transcript log: {}", response_prefix, e);
+                        }
+
+                        if response.is_final
+                            && let (Some(writer), Some(alternative)) =
+                                (self.subtitle_writer.as_mut(), channel.alternatives.first())
+                        {
+                            let words: Vec<CueWord> = alternative
+                                .words
+                                .iter()
+                                .map(|word| CueWord {
+                                    text: word.punctuated_word.as_deref().unwrap_or(&word.word),
+                                    start: word.start,
+                                    end: word.end,
+                                    speaker: word.speaker,
+                                })
+                                .collect();
+                            if let Err(e) = writer.write_words(&words) {
+                                eprintln!("{}Failed to write subtitles: {}", response_prefix, e);
+                            }
+                        }
+                        for alternative in channel.alternatives {
+                            if !alternative.transcript.trim().is_empty() && !silent {
+                                if !response.is_final {
+                                    // Interim results are redrawn dimmed in place until a
+                                    // final result commits the line.
+                                    print!(
+                                        "\r\x1b[2K{}\x1b[2m{}\x1b[0m",
+                                        response_prefix, alternative.transcript
+                                    );
+                                    let _ = std::io::stdout().flush();
+                                } else if diarize && !alternative.words.is_empty() {
+                                    // Group consecutive words by speaker
+                                    let mut segments: Vec<(u32, Vec<&Word>)> = Vec::new();
+                                    for word in &alternative.words {
+                                        let speaker = word.speaker.unwrap_or(0);
+                                        if let Some(last) = segments.last_mut() {
+                                            if last.0 == speaker {
+                                                last.1.push(word);
+                                                continue;
+                                            }
+                                        }
+                                        segments.push((speaker, vec![word]));
+                                    }
+                                    for (speaker, words) in &segments {
+                                        let text = language_tagged(words.iter().copied())
+                                            .unwrap_or_else(|| {
+                                                words
+                                                    .iter()
+                                                    .map(|word| word.word.as_str())
+                                                    .collect::<Vec<_>>()
+                                                    .join(" ")
+                                            });
+                                        println!(
+                                            "\r\x1b[2K{}Speaker {}: {}",
+                                            response_prefix, speaker, text
+                                        );
+                                    }
+                                } else {
+                                    print!(
+                                        "\r\x1b[2K{}Transcript: {}",
+                                        response_prefix,
+                                        language_tagged(&alternative.words)
+                                            .as_deref()
+                                            .unwrap_or(&alternative.transcript)
+                                    );
+                                    if let Some(confidence) = alternative.confidence {
+                                        print!(" (Confidence: {:.1}%)", confidence * 100.0);
+                                    }
+                                    println!();
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    if let Some(server) = &self.server {
+                        self.publish_event(server, &response);
+                    }
+                    if let Some(line) = event_line(&response, self.time_offset)
+                        && !silent
+                    {
+                        println!("\r\x1b[2K{}{}", response_prefix, line);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to parse response: {}", e);
+                if let Ok(mut f) = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open("dg-stt-debug.log")
+                {
+                    let _ = writeln!(f, "--- parse error: {} ---", e);
+                    let _ = writeln!(f, "{}", text);
+                }
+            }
+        }
+    }
+
+    /// Republish a SpeechStarted or UtteranceEnd event on the rebased timeline.
+    fn publish_event(&self, server: &TranscriptServer, response: &DeepgramResponse) {
+        let connection = self.connection_id;
+        let channel_label = self.channel_label.as_deref();
+        let offset = self.time_offset;
+        match response.message_type.as_str() {
+            "SpeechStarted" => server.publish(&ServeEvent::SpeechStarted {
+                connection,
+                channel_label,
+                timestamp: response.timestamp.map(|timestamp| timestamp + offset),
+            }),
+            "UtteranceEnd" => server.publish(&ServeEvent::UtteranceEnd {
+                connection,
+                channel_label,
+                last_word_end: response.last_word_end.map(|end| end + offset),
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Words with a `[lang]` tag wherever the language changes, for results from
+/// `language=multi`. `None` when the words carry no language.
+fn language_tagged<'a>(words: impl IntoIterator<Item = &'a Word>) -> Option<String> {
+    let mut current: Option<&str> = None;
+    let mut text = String::new();
+    for word in words {
+        if let Some(language) = word.language.as_deref()
+            && current != Some(language)
+        {
+            text.push_str(&format!("[{language}] "));
+            current = Some(language);
+        }
+        text.push_str(word.punctuated_word.as_deref().unwrap_or(&word.word));
+        text.push(' ');
+    }
+    current.map(|_| text.trim_end().to_string())
+}
+
+/// Describe a SpeechStarted or UtteranceEnd event on the rebased timeline.
+fn event_line(response: &DeepgramResponse, offset: f64) -> Option<String> {
+    match response.message_type.as_str() {
+        "SpeechStarted" => Some(match response.timestamp {
+            Some(timestamp) => format!("[SpeechStarted at {:.2}s]", timestamp + offset),
+            None => "[SpeechStarted]".to_string(),
+        }),
+        "UtteranceEnd" => Some(match response.last_word_end {
+            Some(last_word_end) => format!(
+                "[UtteranceEnd, last word ended at {:.2}s]",
+                last_word_end + offset
+            ),
+            None => "[UtteranceEnd]".to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Shift a Results message and its word timings onto the continuous timeline.
+fn rebase_results(response: &mut DeepgramResponse, channel: &mut Channel, offset: f64) {
+    if offset == 0.0 {
+        return;
+    }
+    response.start += offset;
+    for alternative in &mut channel.alternatives {
+        for word in &mut alternative.words {
+            word.start += offset;
+            word.end += offset;
+        }
+    }
+}
+
+/// Drop the words of a replayed result that end by `skip_until`, where the
+/// previous session already finalized them, and rebuild the transcript and
+/// timing from the words left. Returns false if nothing is left to show.
+fn skip_finalized_words(
+    response: &mut DeepgramResponse,
+    channel: &mut Channel,
+    skip_until: f64,
+) -> bool {
+    let end = response.start + response.duration;
+    if end <= skip_until {
+        return false;
+    }
+    if response.start >= skip_until {
+        return true;
+    }
+
+    for alternative in &mut channel.alternatives {
+        if !alternative.words.iter().any(|word| word.end <= skip_until) {
+            continue;
+        }
+        alternative.words.retain(|word| word.end > skip_until);
+        alternative.transcript = alternative
+            .words
+            .iter()
+            .map(|word| word.punctuated_word.as_deref().unwrap_or(&word.word))
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    let Some(first) = channel
+        .alternatives
+        .first()
+        .and_then(|alternative| alternative.words.first())
+    else {
+        return false;
+    };
+    response.start = first.start.max(response.start);
+    response.duration = end - response.start;
+    true
+}
+
+/// The most recently streamed audio, kept so it can be replayed after a
+/// reconnect. Whole chunks are kept, so the buffer may exceed its capacity by
+/// up to one chunk.
+struct ReplayBuffer {
+    chunks: VecDeque<Vec<u8>>,
+    buffered_bytes: usize,
+    total_bytes: u64,
+    capacity: usize,
+}
+
+impl ReplayBuffer {
+    fn new(capacity: usize) -> Self {
+        ReplayBuffer {
+            chunks: VecDeque::new(),
+            buffered_bytes: 0,
+            total_bytes: 0,
+            capacity,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        self.total_bytes += chunk.len() as u64;
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.chunks.push_back(chunk.to_vec());
+        self.buffered_bytes += chunk.len();
+        while self.buffered_bytes > self.capacity && self.chunks.len() > 1 {
+            if let Some(front) = self.chunks.pop_front() {
+                self.buffered_bytes -= front.len();
+            }
+        }
+    }
+
+    fn chunks(&self) -> impl Iterator<Item = &Vec<u8>> {
+        self.chunks.iter()
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
+
+    /// Byte position in the overall stream of the first buffered byte.
+    fn start_bytes(&self) -> u64 {
+        self.total_bytes - self.buffered_bytes as u64
+    }
 }
 
 fn parse_redact_entities(redact_value: &str) -> Vec<String> {
@@ -413,3 +1152,334 @@ fn parse_redact_entities(redact_value: &str) -> Vec<String> {
 
     entities
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ReplayBuffer, ResponseState, SessionEnd, StreamInputs, event_line, handshake_status,
+        language_tagged, ramp_up_delay, rebase_results, run_session,
+    };
+    use crate::controls::StreamControl;
+    use crate::protocol::{Channel, DeepgramResponse, StreamError, Word};
+    use crate::transcript_log::TranscriptLog;
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::tungstenite::Message;
+
+    #[test]
+    fn ramp_up_spreads_connection_starts_evenly() {
+        let delays: Vec<Duration> = (1..=5).map(|id| ramp_up_delay(Some(8.0), id, 5)).collect();
+        assert_eq!(delays, [0, 2, 4, 6, 8].map(Duration::from_secs).to_vec());
+        assert_eq!(ramp_up_delay(Some(8.0), 1, 1), Duration::ZERO);
+        assert_eq!(ramp_up_delay(None, 3, 5), Duration::ZERO);
+    }
+
+    #[test]
+    fn extracts_status_of_rejected_handshake() {
+        let response = tokio_tungstenite::tungstenite::http::Response::builder()
+            .status(429)
+            .body(None)
+            .unwrap();
+        let rejected: StreamError = Box::new(tokio_tungstenite::tungstenite::Error::Http(
+            Box::new(response),
+        ));
+        assert_eq!(handshake_status(&rejected), Some(429));
+        assert_eq!(handshake_status(&"connection refused".into()), None);
+    }
+
+    #[test]
+    fn replay_buffer_keeps_most_recent_chunks_within_capacity() {
+        let mut replay = ReplayBuffer::new(6);
+        for chunk in [[1u8; 4], [2; 4], [3; 4]] {
+            replay.push(&chunk);
+        }
+
+        assert_eq!(replay.buffered_bytes(), 4);
+        assert_eq!(replay.start_bytes(), 8);
+        assert_eq!(replay.chunks().collect::<Vec<_>>(), vec![&vec![3u8; 4]]);
+
+        let mut disabled = ReplayBuffer::new(0);
+        disabled.push(&[0; 10]);
+        assert_eq!(disabled.buffered_bytes(), 0);
+        assert_eq!(disabled.start_bytes(), 10);
+    }
+
+    #[test]
+    fn rebasing_shifts_result_and_word_timestamps() {
+        let mut response: DeepgramResponse = serde_json::from_str(
+            r#"{"type":"Results","start":1.0,"duration":2.0,"is_final":true}"#,
+        )
+        .unwrap();
+        let mut channel: Channel = serde_json::from_str(
+            r#"{"alternatives":[{"transcript":"hi","words":[{"word":"hi","start":1.5,"end":2.0}]}]}"#,
+        )
+        .unwrap();
+
+        rebase_results(&mut response, &mut channel, 10.0);
+
+        assert_eq!(response.start, 11.0);
+        assert_eq!(response.duration, 2.0);
+        assert_eq!(channel.alternatives[0].words[0].start, 11.5);
+        assert_eq!(channel.alternatives[0].words[0].end, 12.0);
+    }
+
+    #[test]
+    fn parses_full_results_schema() {
+        let response: DeepgramResponse = serde_json::from_str(
+            r#"{"type":"Results","channel_index":[1,2],"duration":1.5,"start":3.0,
+                "is_final":true,"speech_final":false,"from_finalize":true,
+                "channel":{"alternatives":[{"transcript":"hi","confidence":0.9,"words":[]}]},
+                "metadata":{"request_id":"abc"}}"#,
+        )
+        .unwrap();
+
+        assert!(response.is_final);
+        assert!(!response.speech_final);
+        assert!(response.from_finalize);
+        assert_eq!(response.channel_index, vec![1, 2]);
+        assert_eq!((response.start, response.duration), (3.0, 1.5));
+    }
+
+    #[test]
+    fn describes_speech_started_and_utterance_end_events() {
+        let speech_started: DeepgramResponse =
+            serde_json::from_str(r#"{"type":"SpeechStarted","channel":[0,1],"timestamp":1.25}"#)
+                .unwrap();
+        let utterance_end: DeepgramResponse =
+            serde_json::from_str(r#"{"type":"UtteranceEnd","channel":[0,1],"last_word_end":2.5}"#)
+                .unwrap();
+        let metadata: DeepgramResponse =
+            serde_json::from_str(r#"{"type":"Metadata","request_id":"abc"}"#).unwrap();
+
+        assert_eq!(
+            event_line(&speech_started, 10.0).as_deref(),
+            Some("[SpeechStarted at 11.25s]")
+        );
+        assert_eq!(
+            event_line(&utterance_end, 0.0).as_deref(),
+            Some("[UtteranceEnd, last word ended at 2.50s]")
+        );
+        assert_eq!(event_line(&metadata, 0.0), None);
+    }
+
+    #[test]
+    fn tags_words_where_the_language_changes() {
+        let words: Vec<Word> = serde_json::from_str(
+            r#"[
+                {"word":"hola","language":"es"},
+                {"word":"buenos","language":"es"},
+                {"word":"días","punctuated_word":"días."},
+                {"word":"how","punctuated_word":"How","language":"en"},
+                {"word":"are","language":"en"},
+                {"word":"you","language":"en"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            language_tagged(&words).as_deref(),
+            Some("[es] hola buenos días. [en] How are you")
+        );
+
+        let monolingual: Vec<Word> = serde_json::from_str(r#"[{"word":"hello"}]"#).unwrap();
+        assert_eq!(language_tagged(&monolingual), None);
+    }
+
+    fn silent_state() -> Arc<Mutex<ResponseState>> {
+        Arc::new(Mutex::new(ResponseState {
+            prefix: String::new(),
+            silent: true,
+            diarize: false,
+            subtitle_writer: None,
+            transcript_log: None,
+            connection_id: 1,
+            channel_label: None,
+            server: None,
+            request_id: None,
+            time_offset: 0.0,
+            last_final_end: 0.0,
+            skip_until: None,
+            latency: None,
+            bytes_per_second: 32000.0,
+            audio_sent_seconds: 0.0,
+            finalized_since_audio: false,
+        }))
+    }
+
+    #[test]
+    fn replayed_results_do_not_repeat_finalized_words() {
+        let path = std::env::temp_dir().join(format!("dg-stt-skip-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let state = silent_state();
+        let mut state = state.lock().unwrap();
+        state.transcript_log = Some(TranscriptLog::open(&path).unwrap());
+        let result = |start: f64, duration: f64, words: &[(&str, f64, f64)]| {
+            let words: Vec<serde_json::Value> = words
+                .iter()
+                .map(|(word, start, end)| serde_json::json!({"word": word, "start": start, "end": end}))
+                .collect();
+            let transcript: Vec<&str> = words.iter().map(|w| w["word"].as_str().unwrap()).collect();
+            serde_json::json!({
+                "type": "Results",
+                "is_final": true,
+                "start": start,
+                "duration": duration,
+                "channel": {"alternatives": [{"transcript": transcript.join(" "), "words": words}]}
+            })
+            .to_string()
+        };
+
+        state.handle_message(&result(
+            0.0,
+            2.0,
+            &[("one", 0.0, 0.5), ("two", 0.5, 1.0), ("three", 1.0, 1.8)],
+        ));
+        assert_eq!(state.last_final_end, 2.0);
+
+        // Reconnected, replaying from 1.0s: the new session splits its finals
+        // differently, so its first final straddles the last one printed.
+        state.time_offset = 1.0;
+        state.skip_until = Some(state.last_final_end);
+        state.handle_message(&result(0.0, 0.9, &[("three", 0.0, 0.8)]));
+        state.handle_message(&result(
+            0.0,
+            2.5,
+            &[("three", 0.0, 0.8), ("four", 1.2, 1.6), ("five", 2.0, 2.4)],
+        ));
+        drop(state);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<serde_json::Value> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let transcripts: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry["transcript"].as_str().unwrap())
+            .collect();
+        assert_eq!(transcripts, ["one two three", "four five"]);
+        assert!((entries[1]["start"].as_f64().unwrap() - 2.2).abs() < 1e-9);
+        assert_eq!(entries[1]["words"].as_array().unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pausing_longer_than_the_idle_timeout_keeps_the_session_open() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // A server that never answers audio, so only the pause keeps the
+        // session from timing out, and closes on CloseStream.
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut audio = Vec::new();
+            while let Some(Ok(message)) = websocket.next().await {
+                match message {
+                    Message::Binary(data) => audio.push(data.to_vec()),
+                    Message::Text(text) if text.contains("CloseStream") => {
+                        let _ = websocket.send(Message::Close(None)).await;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            audio
+        });
+        let (ws_stream, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+            .await
+            .unwrap();
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (_shutdown_tx, shutdown_rx) = mpsc::channel(1);
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let mut inputs = StreamInputs {
+            audio_rx,
+            shutdown_rx,
+            control_rx: Some(control_rx),
+            paused: false,
+        };
+        tokio::spawn(async move {
+            control_tx.send(StreamControl::TogglePause).unwrap();
+            tokio::time::sleep(Duration::from_millis(600)).await;
+            control_tx.send(StreamControl::TogglePause).unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            audio_tx.send(vec![1; 32]).unwrap();
+            // Dropping the sender ends the source, which sends CloseStream.
+        });
+
+        let mut audio_count = 0;
+        let session_end = run_session(
+            ws_stream,
+            "",
+            &mut inputs,
+            &mut ReplayBuffer::new(0),
+            silent_state(),
+            &mut audio_count,
+            Duration::from_millis(200),
+        )
+        .await;
+
+        assert_eq!(session_end, SessionEnd::Finished);
+        assert_eq!(audio_count, 1);
+        assert_eq!(server.await.unwrap(), [vec![1u8; 32]]);
+    }
+
+    #[tokio::test]
+    async fn socket_dropped_after_close_stream_and_final_result_finishes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // A server that finalizes each chunk of audio, then drops the TCP
+        // connection on CloseStream without a close frame.
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(message)) = websocket.next().await {
+                match message {
+                    Message::Binary(_) => {
+                        let result = r#"{"type":"Results","is_final":true,"start":0.0,"duration":1.0,
+                            "channel":{"alternatives":[{"transcript":"hello","words":[]}]}}"#;
+                        websocket.send(Message::Text(result.into())).await.unwrap();
+                    }
+                    Message::Text(text) if text.contains("CloseStream") => break,
+                    _ => {}
+                }
+            }
+        });
+        let (ws_stream, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+            .await
+            .unwrap();
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (_shutdown_tx, shutdown_rx) = mpsc::channel(1);
+        let mut inputs = StreamInputs {
+            audio_rx,
+            shutdown_rx,
+            control_rx: None,
+            paused: false,
+        };
+        let state = silent_state();
+        let finalized = state.clone();
+        tokio::spawn(async move {
+            audio_tx.send(vec![1; 32]).unwrap();
+            // Close only once the audio has been finalized.
+            while !finalized.lock().unwrap().finalized_since_audio {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let session_end = run_session(
+            ws_stream,
+            "",
+            &mut inputs,
+            &mut ReplayBuffer::new(0),
+            state,
+            &mut 0,
+            Duration::from_secs(5),
+        )
+        .await;
+
+        assert_eq!(session_end, SessionEnd::Finished);
+    }
+}
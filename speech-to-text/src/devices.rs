@@ -0,0 +1,261 @@
+use clap::Args;
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Device, Host, SupportedBufferSize};
+
+/// Input device selection for `stream microphone`.
+#[derive(Args, Clone, Debug)]
+pub struct InputDeviceArgs {
+    /// Audio host (backend) to capture from, e.g. alsa, jack, wasapi, asio, or coreaudio.
+    /// Defaults to the platform's default host.
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Input device to capture from, by its index in `dg-stt devices` or by name.
+    /// Names match case-insensitively, and any part of a name that picks out a single
+    /// device is enough (e.g. "headset"). Defaults to the host's default input device.
+    #[arg(long)]
+    pub device: Option<String>,
+
+    /// Capture buffer size in milliseconds instead of the host's default. Smaller
+    /// buffers lower latency; larger ones tolerate a busy machine.
+    #[arg(long)]
+    pub buffer_ms: Option<u32>,
+}
+
+/// The host named by `--host`, or the default host.
+pub(crate) fn select_host(name: Option<&str>) -> Result<Host, Box<dyn std::error::Error>> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+    let hosts = cpal::available_hosts();
+    let names: Vec<String> = hosts.iter().map(|id| id.name().to_string()).collect();
+    let index = match_candidate(name, &names, "audio host")?;
+    Ok(cpal::host_from_id(hosts[index])?)
+}
+
+/// The input device named by `--device` on `host`, or its default input device.
+pub(crate) fn select_input_device(
+    host: &Host,
+    query: Option<&str>,
+) -> Result<Device, Box<dyn std::error::Error>> {
+    let Some(query) = query else {
+        return Ok(host
+            .default_input_device()
+            .ok_or_else(|| format!("No default input device on {}", host.id().name()))?);
+    };
+
+    let mut devices: Vec<Device> = host.input_devices()?.collect();
+    let names: Vec<String> = devices.iter().map(device_name).collect();
+    let index = match query.trim().parse::<usize>() {
+        Ok(index) if index < devices.len() => index,
+        Ok(index) => {
+            return Err(format!(
+                "No input device with index {index} on {}. Available:\n{}",
+                host.id().name(),
+                candidate_list(&names, 0..names.len())
+            )
+            .into());
+        }
+        Err(_) => match_candidate(query, &names, "input device")?,
+    };
+    Ok(devices.swap_remove(index))
+}
+
+fn device_name(device: &Device) -> String {
+    device
+        .name()
+        .unwrap_or_else(|_| "<unnamed device>".to_string())
+}
+
+/// Index of the candidate `query` names. An exact case-insensitive match wins;
+/// otherwise the query must be a substring of exactly one candidate.
+fn match_candidate(query: &str, candidates: &[String], kind: &str) -> Result<usize, String> {
+    let wanted = query.trim().to_lowercase();
+    let lowered: Vec<String> = candidates.iter().map(|name| name.to_lowercase()).collect();
+    if let Some(index) = lowered.iter().position(|name| *name == wanted) {
+        return Ok(index);
+    }
+
+    let matches: Vec<usize> = (0..candidates.len())
+        .filter(|&index| lowered[index].contains(&wanted))
+        .collect();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] if candidates.is_empty() => {
+            Err(format!("No {kind} matches {query:?}; none are available"))
+        }
+        [] => Err(format!(
+            "No {kind} matches {query:?}. Available:\n{}",
+            candidate_list(candidates, 0..candidates.len())
+        )),
+        _ => Err(format!(
+            "{query:?} matches more than one {kind}; use a longer name or the index:\n{}",
+            candidate_list(candidates, matches.iter().copied())
+        )),
+    }
+}
+
+fn candidate_list(candidates: &[String], indices: impl IntoIterator<Item = usize>) -> String {
+    indices
+        .into_iter()
+        .map(|index| format!("  [{index}] {}", candidates[index]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Capture buffer size in frames for `--buffer-ms`, checked against the
+/// device's supported range when it reports one.
+pub(crate) fn buffer_frames(
+    buffer_ms: u32,
+    sample_rate: u32,
+    supported: &SupportedBufferSize,
+) -> Result<u32, String> {
+    let frames = (sample_rate as u64 * buffer_ms as u64 / 1000) as u32;
+    if frames == 0 {
+        return Err("--buffer-ms must be at least one sample long".to_string());
+    }
+    if let SupportedBufferSize::Range { min, max } = *supported
+        && !(min..=max).contains(&frames)
+    {
+        let to_ms = |frames: u32| frames as f64 * 1000.0 / sample_rate as f64;
+        return Err(format!(
+            "--buffer-ms {buffer_ms} is {frames} frames at {sample_rate} Hz; the device supports {min}-{max} frames ({:.1}-{:.1} ms)",
+            to_ms(min),
+            to_ms(max)
+        ));
+    }
+    Ok(frames)
+}
+
+fn describe_buffer_size(supported: &SupportedBufferSize) -> String {
+    match supported {
+        SupportedBufferSize::Range { min, max } => format!("buffer {min}-{max} frames"),
+        SupportedBufferSize::Unknown => "buffer size unknown".to_string(),
+    }
+}
+
+/// Print every input device on each available host (or only `--host`), with
+/// the indices `--device` accepts and each device's supported configurations.
+pub(crate) fn run_list_devices(host: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let default_host = cpal::default_host().id();
+    let hosts = match host.as_deref() {
+        Some(name) => vec![select_host(Some(name))?],
+        None => cpal::available_hosts()
+            .into_iter()
+            .filter_map(|id| match cpal::host_from_id(id) {
+                Ok(host) => Some(host),
+                Err(e) => {
+                    println!("Host: {} (unavailable: {})", id.name(), e);
+                    None
+                }
+            })
+            .collect(),
+    };
+
+    for (host_index, host) in hosts.iter().enumerate() {
+        if host_index > 0 {
+            println!();
+        }
+        let default_marker = if host.id() == default_host {
+            " (default)"
+        } else {
+            ""
+        };
+        println!("Host: {}{}", host.id().name(), default_marker);
+
+        let devices = match host.input_devices() {
+            Ok(devices) => devices.collect::<Vec<_>>(),
+            Err(e) => {
+                println!("  Failed to list input devices: {}", e);
+                continue;
+            }
+        };
+        if devices.is_empty() {
+            println!("  No input devices");
+            continue;
+        }
+        let default_name = host
+            .default_input_device()
+            .map(|device| device_name(&device));
+
+        for (index, device) in devices.iter().enumerate() {
+            let name = device_name(device);
+            let marker = if default_name.as_deref() == Some(name.as_str()) {
+                " (default input)"
+            } else {
+                ""
+            };
+            println!("  [{index}] {name}{marker}");
+            match device.supported_input_configs() {
+                Ok(configs) => {
+                    for config in configs {
+                        let (min, max) = (config.min_sample_rate().0, config.max_sample_rate().0);
+                        let rates = if min == max {
+                            format!("{min} Hz")
+                        } else {
+                            format!("{min}-{max} Hz")
+                        };
+                        println!(
+                            "      {} ch, {}, {}, {}",
+                            config.channels(),
+                            rates,
+                            config.sample_format(),
+                            describe_buffer_size(config.buffer_size())
+                        );
+                    }
+                }
+                Err(e) => println!("      Failed to read supported configs: {}", e),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{buffer_frames, match_candidate};
+    use cpal::SupportedBufferSize;
+
+    #[test]
+    fn matches_names_case_insensitively_by_unique_substring() {
+        let names: Vec<String> = [
+            "default",
+            "Jabra EVOLVE 20 USB Headset",
+            "Loopback Analog Stereo",
+            "Loopback Digital",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        assert_eq!(match_candidate("DEFAULT", &names, "input device"), Ok(0));
+        assert_eq!(match_candidate("headset", &names, "input device"), Ok(1));
+        assert_eq!(match_candidate(" analog ", &names, "input device"), Ok(2));
+
+        let ambiguous = match_candidate("loopback", &names, "input device").unwrap_err();
+        assert_eq!(
+            ambiguous,
+            "\"loopback\" matches more than one input device; use a longer name or the index:\n  \
+             [2] Loopback Analog Stereo\n  [3] Loopback Digital"
+        );
+        let missing = match_candidate("blue yeti", &names, "input device").unwrap_err();
+        assert!(
+            missing
+                .starts_with("No input device matches \"blue yeti\". Available:\n  [0] default\n")
+        );
+    }
+
+    #[test]
+    fn converts_buffer_ms_to_frames_within_the_supported_range() {
+        let range = SupportedBufferSize::Range { min: 64, max: 4096 };
+        assert_eq!(buffer_frames(20, 48000, &range), Ok(960));
+        assert_eq!(
+            buffer_frames(500, 48000, &SupportedBufferSize::Unknown),
+            Ok(24000)
+        );
+        assert_eq!(
+            buffer_frames(100, 48000, &range).unwrap_err(),
+            "--buffer-ms 100 is 4800 frames at 48000 Hz; the device supports 64-4096 frames (1.3-85.3 ms)"
+        );
+        assert!(buffer_frames(0, 48000, &SupportedBufferSize::Unknown).is_err());
+    }
+}
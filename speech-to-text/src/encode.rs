@@ -0,0 +1,413 @@
+use audiopus::coder::Encoder as OpusCoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use tokio::sync::mpsc;
+
+use crate::audio::bytes_per_sample;
+
+/// Opus frame length. 20 ms is what telephony integrations typically send.
+const OPUS_FRAME_MS: u32 = 20;
+
+/// Constant Opus bitrate per channel, so the stream has a fixed byte rate for
+/// reconnect replay and latency measurement.
+const OPUS_BITRATE_PER_CHANNEL: i32 = 32_000;
+
+/// Largest Opus packet the encoder may produce.
+const MAX_OPUS_PACKET: usize = 4000;
+
+/// Encodings `--encoding` can produce from linear16 audio.
+const SUPPORTED: &str = "linear16, mulaw, alaw, opus, ogg-opus";
+
+/// Encodes linear16 audio into the `--encoding` sent to Deepgram.
+pub(crate) enum AudioEncoder {
+    Mulaw,
+    Alaw,
+    Opus(OpusEncoder),
+}
+
+impl AudioEncoder {
+    /// The encoder for `encoding`, or `None` when linear16 is sent as-is.
+    pub(crate) fn for_encoding(
+        encoding: &str,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        match encoding.to_ascii_lowercase().as_str() {
+            "linear16" => Ok(None),
+            "mulaw" => Ok(Some(AudioEncoder::Mulaw)),
+            "alaw" => Ok(Some(AudioEncoder::Alaw)),
+            "opus" => Ok(Some(AudioEncoder::Opus(OpusEncoder::new(
+                sample_rate,
+                channels,
+                false,
+            )?))),
+            "ogg-opus" => Ok(Some(AudioEncoder::Opus(OpusEncoder::new(
+                sample_rate,
+                channels,
+                true,
+            )?))),
+            other => Err(format!(
+                "Cannot encode audio as {other}; --encoding must be one of {SUPPORTED}"
+            )
+            .into()),
+        }
+    }
+
+    /// Whether the output is a container that a connection must receive from
+    /// the start, so it cannot be replayed or joined midway.
+    pub(crate) fn needs_stream_start(&self) -> bool {
+        matches!(self, AudioEncoder::Opus(opus) if opus.ogg.is_some())
+    }
+
+    /// Encode a chunk of linear16 audio into the messages to send.
+    pub(crate) fn encode(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, audiopus::Error> {
+        match self {
+            AudioEncoder::Mulaw => Ok(vec![linear16_samples(data).map(mulaw).collect()]),
+            AudioEncoder::Alaw => Ok(vec![linear16_samples(data).map(alaw).collect()]),
+            AudioEncoder::Opus(opus) => opus.encode(data),
+        }
+    }
+
+    /// Flush buffered audio at the end of the stream.
+    pub(crate) fn finish(&mut self) -> Result<Vec<Vec<u8>>, audiopus::Error> {
+        match self {
+            AudioEncoder::Opus(opus) => opus.finish(),
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Bytes per second of audio sent in `encoding`. Compressed encodings other
+/// than the constant-bitrate Opus produced here are assumed to be linear16.
+pub(crate) fn encoded_bytes_per_second(encoding: &str, sample_rate: u32, channels: u16) -> f64 {
+    match encoding.to_ascii_lowercase().as_str() {
+        "opus" | "ogg-opus" => (OPUS_BITRATE_PER_CHANNEL * channels as i32) as f64 / 8.0,
+        encoding => {
+            sample_rate as f64 * channels as f64 * bytes_per_sample(encoding).unwrap_or(2) as f64
+        }
+    }
+}
+
+fn linear16_samples(data: &[u8]) -> impl Iterator<Item = i16> + '_ {
+    data.chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// G.711 μ-law.
+fn mulaw(sample: i16) -> u8 {
+    const BIAS: i32 = 0x84;
+    const CLIP: i32 = 32635;
+
+    let sign = if sample < 0 { 0x80 } else { 0 };
+    let magnitude = (sample as i32).abs().min(CLIP) + BIAS;
+    let exponent = (31 - magnitude.leading_zeros() as i32 - 7).clamp(0, 7);
+    let mantissa = (magnitude >> (exponent + 3)) & 0x0F;
+    !(sign | (exponent << 4) as u8 | mantissa as u8)
+}
+
+/// G.711 A-law.
+fn alaw(sample: i16) -> u8 {
+    let mut pcm = (sample >> 3) as i32;
+    let mask = if pcm >= 0 {
+        0xD5
+    } else {
+        pcm = -pcm - 1;
+        0x55
+    };
+    let segment = (0..8).find(|&segment| pcm < 0x20 << segment);
+    let Some(segment) = segment else {
+        return 0x7F ^ mask;
+    };
+    let shift = if segment < 2 { 1 } else { segment };
+    let value = (segment << 4) as u8 | ((pcm >> shift) & 0x0F) as u8;
+    value ^ mask
+}
+
+/// Constant-bitrate Opus encoder producing raw packets, one per message, or
+/// an Ogg Opus stream.
+pub(crate) struct OpusEncoder {
+    coder: OpusCoder,
+    channels: usize,
+    frame_samples: usize,
+    /// Granule positions count 48 kHz samples regardless of the input rate.
+    granule_step: u64,
+    granule: u64,
+    pending: Vec<i16>,
+    ogg: Option<PacketWriter<Vec<u8>>>,
+}
+
+impl OpusEncoder {
+    const OGG_SERIAL: u32 = 0x6467_7374;
+
+    fn new(sample_rate: u32, channels: u16, ogg: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        let opus_rate = SampleRate::try_from(sample_rate as i32).map_err(|_| {
+            format!(
+                "Opus cannot encode {sample_rate} Hz audio; add --target-sample-rate with 8000, 12000, 16000, 24000, or 48000"
+            )
+        })?;
+        let opus_channels = match channels {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            _ => {
+                return Err(format!(
+                    "Opus cannot encode {channels} channels here; add --mono to downmix"
+                )
+                .into());
+            }
+        };
+
+        let mut coder = OpusCoder::new(opus_rate, opus_channels, Application::Voip)?;
+        coder.set_bitrate(Bitrate::BitsPerSecond(
+            OPUS_BITRATE_PER_CHANNEL * channels as i32,
+        ))?;
+        coder.disable_vbr()?;
+
+        let frame_samples = (sample_rate * OPUS_FRAME_MS / 1000) as usize;
+        let granule_step = (48_000 * OPUS_FRAME_MS / 1000) as u64;
+        let ogg = ogg.then(|| PacketWriter::new(Vec::new()));
+        let mut encoder = OpusEncoder {
+            coder,
+            channels: channels as usize,
+            frame_samples,
+            granule_step,
+            granule: 0,
+            pending: Vec::new(),
+            ogg,
+        };
+        encoder.write_ogg_headers(sample_rate)?;
+        Ok(encoder)
+    }
+
+    /// Queue the OpusHead and OpusTags pages, which go out with the first audio.
+    fn write_ogg_headers(&mut self, sample_rate: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let pre_skip = self.coder.lookahead()? as u64 * 48_000 / sample_rate as u64;
+        let Some(ogg) = self.ogg.as_mut() else {
+            return Ok(());
+        };
+
+        let mut head = b"OpusHead".to_vec();
+        head.push(1);
+        head.push(self.channels as u8);
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&sample_rate.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+        ogg.write_packet(
+            head.into_boxed_slice(),
+            Self::OGG_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+
+        let vendor = concat!("dg-stt ", env!("CARGO_PKG_VERSION"));
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        ogg.write_packet(
+            tags.into_boxed_slice(),
+            Self::OGG_SERIAL,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+        Ok(())
+    }
+
+    fn encode(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>, audiopus::Error> {
+        self.pending.extend(linear16_samples(data));
+        let frame_len = self.frame_samples * self.channels;
+        let mut packets = Vec::new();
+        while self.pending.len() >= frame_len {
+            let frame: Vec<i16> = self.pending.drain(..frame_len).collect();
+            packets.push(self.encode_frame(&frame)?);
+        }
+        Ok(self.package(packets, false))
+    }
+
+    fn finish(&mut self) -> Result<Vec<Vec<u8>>, audiopus::Error> {
+        let mut packets = Vec::new();
+        // An Ogg stream needs a last packet to carry the end-of-stream flag,
+        // so it gets a frame of silence if the audio ended on a frame boundary.
+        if !self.pending.is_empty() || self.ogg.is_some() {
+            let mut frame = std::mem::take(&mut self.pending);
+            frame.resize(self.frame_samples * self.channels, 0);
+            packets.push(self.encode_frame(&frame)?);
+        }
+        Ok(self.package(packets, true))
+    }
+
+    fn encode_frame(&mut self, frame: &[i16]) -> Result<Vec<u8>, audiopus::Error> {
+        let mut packet = vec![0u8; MAX_OPUS_PACKET];
+        let len = self.coder.encode(frame, &mut packet)?;
+        packet.truncate(len);
+        Ok(packet)
+    }
+
+    /// Raw packets are sent one per message. Ogg pages for all packets of a
+    /// chunk go in one message, each packet ending its page so nothing waits
+    /// for a page to fill.
+    fn package(&mut self, packets: Vec<Vec<u8>>, end_of_stream: bool) -> Vec<Vec<u8>> {
+        let Some(ogg) = self.ogg.as_mut() else {
+            return packets;
+        };
+
+        let count = packets.len();
+        for (idx, packet) in packets.into_iter().enumerate() {
+            self.granule += self.granule_step;
+            let end = if end_of_stream && idx + 1 == count {
+                PacketWriteEndInfo::EndStream
+            } else {
+                PacketWriteEndInfo::EndPage
+            };
+            // Writing into a Vec cannot fail.
+            let _ = ogg.write_packet(
+                packet.into_boxed_slice(),
+                Self::OGG_SERIAL,
+                end,
+                self.granule,
+            );
+        }
+        let pages = std::mem::take(ogg.inner_mut());
+        if pages.is_empty() {
+            Vec::new()
+        } else {
+            vec![pages]
+        }
+    }
+}
+
+/// Encode audio from `source_rx` in a background task, returning the encoded
+/// messages. The task ends when the source does.
+pub(crate) fn start_audio_encoding(
+    mut source_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    mut encoder: AudioEncoder,
+) -> mpsc::UnboundedReceiver<Vec<u8>> {
+    let (tx, rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        while let Some(chunk) = source_rx.recv().await {
+            let messages = match encoder.encode(&chunk) {
+                Ok(messages) => messages,
+                Err(e) => {
+                    eprintln!("Audio encoding failed: {e}");
+                    return;
+                }
+            };
+            for message in messages {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+        }
+        match encoder.finish() {
+            Ok(messages) => {
+                for message in messages {
+                    let _ = tx.send(message);
+                }
+            }
+            Err(e) => eprintln!("Audio encoding failed: {e}"),
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AudioEncoder, alaw, encoded_bytes_per_second, mulaw};
+    use ogg::reading::PacketReader;
+    use std::io::Cursor;
+
+    fn mulaw_decode(byte: u8) -> i16 {
+        let byte = !byte;
+        let exponent = (byte >> 4) & 0x07;
+        let mantissa = (byte & 0x0F) as i32;
+        let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+        (if byte & 0x80 != 0 {
+            -magnitude
+        } else {
+            magnitude
+        }) as i16
+    }
+
+    fn alaw_decode(byte: u8) -> i16 {
+        let byte = byte ^ 0x55;
+        let segment = (byte >> 4) & 0x07;
+        let mut magnitude = ((byte & 0x0F) as i32) << 4;
+        magnitude = match segment {
+            0 => magnitude + 8,
+            1 => magnitude + 0x108,
+            _ => (magnitude + 0x108) << (segment - 1),
+        };
+        (if byte & 0x80 != 0 {
+            magnitude
+        } else {
+            -magnitude
+        }) as i16
+    }
+
+    #[test]
+    fn g711_matches_reference_codes_and_round_trips() {
+        assert_eq!((mulaw(0), mulaw(-1), mulaw(i16::MAX)), (0xFF, 0x7F, 0x80));
+        assert_eq!((alaw(0), alaw(-8), alaw(i16::MAX)), (0xD5, 0x55, 0xAA));
+
+        for sample in (i16::MIN..=i16::MAX).step_by(97) {
+            // Quantization error grows with magnitude but stays within one
+            // step of the segment, about 1/16 of the value.
+            let tolerance = (sample as i32).abs() / 16 + 40;
+            let mu = mulaw_decode(mulaw(sample)) as i32;
+            let a = alaw_decode(alaw(sample)) as i32;
+            assert!(
+                (mu - sample as i32).abs() <= tolerance,
+                "mulaw {sample} -> {mu}"
+            );
+            assert!(
+                (a - sample as i32).abs() <= tolerance,
+                "alaw {sample} -> {a}"
+            );
+        }
+    }
+
+    #[test]
+    fn opus_sends_constant_bitrate_packets_or_an_ogg_stream() {
+        let second: Vec<u8> = (0..16000)
+            .map(|n| ((n as f64 / 5.0).sin() * 8000.0) as i16)
+            .flat_map(i16::to_le_bytes)
+            .collect();
+
+        let mut raw = AudioEncoder::for_encoding("opus", 16000, 1)
+            .unwrap()
+            .unwrap();
+        let mut packets = raw.encode(&second[..20000]).unwrap();
+        packets.extend(raw.encode(&second[20000..]).unwrap());
+        packets.extend(raw.finish().unwrap());
+        assert_eq!(packets.len(), 50);
+        let bytes: usize = packets.iter().map(Vec::len).sum();
+        assert_eq!(bytes as f64, encoded_bytes_per_second("opus", 16000, 1));
+
+        let mut ogg = AudioEncoder::for_encoding("ogg-opus", 16000, 1)
+            .unwrap()
+            .unwrap();
+        assert!(ogg.needs_stream_start());
+        let mut stream = ogg.encode(&second).unwrap().concat();
+        stream.extend(ogg.finish().unwrap().concat());
+        let mut reader = PacketReader::new(Cursor::new(stream));
+        let mut packets = Vec::new();
+        while let Some(packet) = reader.read_packet().unwrap() {
+            packets.push(packet);
+        }
+        assert!(packets[0].data.starts_with(b"OpusHead"));
+        assert!(packets[1].data.starts_with(b"OpusTags"));
+        // 50 frames of audio, then a frame of silence carrying end-of-stream.
+        assert_eq!(packets.len(), 2 + 51);
+        let last = packets.last().unwrap();
+        assert!(last.last_in_stream());
+        assert_eq!(last.absgp_page(), 51 * 960);
+
+        assert!(AudioEncoder::for_encoding("opus", 44100, 1).is_err());
+        assert!(AudioEncoder::for_encoding("flac", 16000, 1).is_err());
+        assert!(
+            AudioEncoder::for_encoding("linear16", 16000, 1)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use clap::Args;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::transcribe::{
+    TranscribeOptions, build_listen_url, post_audio, response_summary, response_transcript,
+};
+use crate::wer::{ErrorCounts, normalize, score, split_words};
+
+#[derive(Args)]
+pub struct EvalArgs {
+    /// JSONL manifest with one {"audio": ..., "reference": ...} or
+    /// {"audio": ..., "reference_file": ...} object per line. Relative paths are
+    /// resolved against the manifest's directory.
+    #[arg(short, long)]
+    pub manifest: PathBuf,
+
+    /// Number of transcription requests to run concurrently
+    #[arg(long, default_value_t = 4)]
+    pub concurrency: usize,
+
+    /// Score the raw text instead of normalizing case, punctuation, and numerals
+    #[arg(long)]
+    pub no_normalize: bool,
+
+    /// Include the normalized reference and hypothesis text of each file in the report
+    #[arg(long)]
+    pub show_text: bool,
+
+    /// Query options for the transcription requests. --output selects the report
+    /// format: text (default) or json.
+    #[command(flatten)]
+    pub options: TranscribeOptions,
+}
+
+/// One manifest line. Exactly one of `reference` or `reference_file` is set.
+#[derive(Debug, Deserialize)]
+struct EvalManifestEntry {
+    audio: PathBuf,
+    #[serde(default)]
+    reference: Option<String>,
+    #[serde(default)]
+    reference_file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct EvalJob {
+    audio: PathBuf,
+    reference: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FileScore {
+    audio: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wer: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cer: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    words: Option<ErrorCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    characters: Option<ErrorCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hypothesis: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Totals over every file that was scored. Rates are computed from the summed
+/// counts, so longer files weigh more than shorter ones.
+#[derive(Debug, Serialize)]
+struct AggregateScore {
+    files: usize,
+    failed: usize,
+    wer: f64,
+    cer: f64,
+    words: ErrorCounts,
+    characters: ErrorCounts,
+}
+
+#[derive(Debug, Serialize)]
+struct EvalReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    normalized: bool,
+    files: Vec<FileScore>,
+    aggregate: AggregateScore,
+}
+
+fn parse_manifest(manifest: &Path) -> Result<Vec<EvalJob>, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(manifest)
+        .map_err(|e| format!("Failed to read manifest {}: {e}", manifest.display()))?;
+    let base_dir = manifest.parent().unwrap_or(Path::new(""));
+
+    let mut jobs = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index + 1;
+        let entry: EvalManifestEntry = serde_json::from_str(line).map_err(|e| {
+            format!(
+                "{}:{line_number}: invalid manifest entry: {e}",
+                manifest.display()
+            )
+        })?;
+        let reference = match (entry.reference, entry.reference_file) {
+            (Some(reference), None) => reference,
+            (None, Some(reference_file)) => {
+                let path = base_dir.join(reference_file);
+                std::fs::read_to_string(&path).map_err(|e| {
+                    format!(
+                        "{}:{line_number}: failed to read {}: {e}",
+                        manifest.display(),
+                        path.display()
+                    )
+                })?
+            }
+            _ => {
+                return Err(format!(
+                    "{}:{line_number}: set exactly one of \"reference\" or \"reference_file\"",
+                    manifest.display()
+                )
+                .into());
+            }
+        };
+        jobs.push(EvalJob {
+            audio: base_dir.join(entry.audio),
+            reference,
+        });
+    }
+    Ok(jobs)
+}
+
+async fn evaluate_file(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<&str>,
+    job: &EvalJob,
+    normalized: bool,
+    show_text: bool,
+) -> FileScore {
+    let result: Result<(Option<String>, String), Box<dyn std::error::Error>> = async {
+        let audio_data = tokio::fs::read(&job.audio).await?;
+        let response_text = post_audio(client, url, api_key, audio_data).await?;
+        let request_id = response_summary(&response_text).map(|(request_id, _)| request_id);
+        Ok((request_id, response_transcript(&response_text)?))
+    }
+    .await;
+
+    match result {
+        Ok((request_id, transcript)) => {
+            let tokenize = if normalized { normalize } else { split_words };
+            let reference = tokenize(&job.reference);
+            let hypothesis = tokenize(&transcript);
+            let (words, characters) = score(&reference, &hypothesis);
+            FileScore {
+                audio: job.audio.clone(),
+                request_id,
+                wer: Some(words.rate()),
+                cer: Some(characters.rate()),
+                words: Some(words),
+                characters: Some(characters),
+                reference: show_text.then(|| reference.join(" ")),
+                hypothesis: show_text.then(|| hypothesis.join(" ")),
+                error: None,
+            }
+        }
+        Err(e) => FileScore {
+            audio: job.audio.clone(),
+            request_id: None,
+            wer: None,
+            cer: None,
+            words: None,
+            characters: None,
+            reference: None,
+            hypothesis: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+fn aggregate(files: &[FileScore]) -> AggregateScore {
+    let mut words = ErrorCounts::default();
+    let mut characters = ErrorCounts::default();
+    let mut scored = 0;
+    for file in files {
+        if let (Some(file_words), Some(file_characters)) = (&file.words, &file.characters) {
+            words.add(file_words);
+            characters.add(file_characters);
+            scored += 1;
+        }
+    }
+    AggregateScore {
+        files: scored,
+        failed: files.len() - scored,
+        wer: words.rate(),
+        cer: characters.rate(),
+        words,
+        characters,
+    }
+}
+
+fn render_report(report: &EvalReport) -> String {
+    let row = |name: &str, wer: f64, cer: f64, words: &ErrorCounts| {
+        format!(
+            "{:<40} {:>7.2}% {:>7.2}% {:>6} {:>6} {:>6} {:>7}\n",
+            name,
+            wer * 100.0,
+            cer * 100.0,
+            words.substitutions,
+            words.insertions,
+            words.deletions,
+            words.reference_len
+        )
+    };
+
+    let mut out = String::from("=== Evaluation Results ===\n");
+    if let Some(model) = &report.model {
+        out.push_str(&format!("Model: {}\n", model));
+    }
+    if let Some(version) = &report.version {
+        out.push_str(&format!("Version: {}\n", version));
+    }
+    out.push_str(&format!(
+        "Normalization: {}\n\n",
+        if report.normalized { "on" } else { "off" }
+    ));
+    out.push_str(&format!(
+        "{:<40} {:>8} {:>8} {:>6} {:>6} {:>6} {:>7}\n",
+        "File", "WER", "CER", "Sub", "Ins", "Del", "Words"
+    ));
+
+    for file in &report.files {
+        let name = file.audio.display().to_string();
+        match (&file.wer, &file.cer, &file.words) {
+            (Some(wer), Some(cer), Some(words)) => {
+                out.push_str(&row(&name, *wer, *cer, words));
+                if let (Some(reference), Some(hypothesis)) = (&file.reference, &file.hypothesis) {
+                    out.push_str(&format!("  REF: {}\n  HYP: {}\n", reference, hypothesis));
+                }
+            }
+            _ => out.push_str(&format!(
+                "{:<40} FAILED: {}\n",
+                name,
+                file.error.as_deref().unwrap_or("unknown error")
+            )),
+        }
+    }
+
+    let total = &report.aggregate;
+    out.push('\n');
+    out.push_str(&row(
+        &format!("Aggregate ({} files)", total.files),
+        total.wer,
+        total.cer,
+        &total.words,
+    ));
+    if total.failed > 0 {
+        out.push_str(&format!(
+            "{} files failed and are not scored\n",
+            total.failed
+        ));
+    }
+    out
+}
+
+pub async fn run_eval_mode(
+    api_key: Option<String>,
+    args: EvalArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.concurrency == 0 {
+        return Err("--concurrency must be at least 1".into());
+    }
+    let json = match args.options.output.as_str() {
+        "text" => false,
+        "json" => true,
+        other => {
+            return Err(format!("eval supports --output text or json, not {other}").into());
+        }
+    };
+
+    let jobs = parse_manifest(&args.manifest)?;
+    if jobs.is_empty() {
+        return Err(format!("No entries found in manifest {}", args.manifest.display()).into());
+    }
+
+    // Progress goes to stderr so `--output json` leaves stdout as a single document.
+    eprintln!("Evaluating {} files...", jobs.len());
+
+    let client = reqwest::Client::new();
+    let url = build_listen_url(&args.options, None);
+    let normalized = !args.no_normalize;
+    let total = jobs.len();
+
+    let mut results = futures_util::stream::iter(jobs.iter().map(|job| {
+        evaluate_file(
+            &client,
+            &url,
+            api_key.as_deref(),
+            job,
+            normalized,
+            args.show_text,
+        )
+    }))
+    .buffered(args.concurrency);
+
+    let mut files = Vec::with_capacity(total);
+    while let Some(file) = results.next().await {
+        match (&file.wer, &file.error) {
+            (Some(wer), _) => eprintln!(
+                "[{}/{}] {} WER {:.2}%",
+                files.len() + 1,
+                total,
+                file.audio.display(),
+                wer * 100.0
+            ),
+            (None, error) => eprintln!(
+                "[{}/{}] FAILED {}: {}",
+                files.len() + 1,
+                total,
+                file.audio.display(),
+                error.as_deref().unwrap_or("unknown error")
+            ),
+        }
+        files.push(file);
+    }
+
+    let report = EvalReport {
+        model: args.options.model.clone(),
+        version: args.options.version.clone(),
+        normalized,
+        aggregate: aggregate(&files),
+        files,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("\n{}", render_report(&report));
+    }
+
+    if report.aggregate.failed > 0 {
+        return Err(format!(
+            "{} of {} files failed to transcribe",
+            report.aggregate.failed, total
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileScore, aggregate, parse_manifest};
+    use crate::wer::ErrorCounts;
+    use std::path::PathBuf;
+
+    #[test]
+    fn manifest_resolves_paths_and_reference_files() {
+        let dir = std::env::temp_dir().join(format!("dg-stt-eval-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.txt"), "from a file\n").unwrap();
+        let manifest = dir.join("eval.jsonl");
+        std::fs::write(
+            &manifest,
+            "{\"audio\": \"a.wav\", \"reference\": \"inline text\"}\n\n\
+             {\"audio\": \"/abs/b.wav\", \"reference_file\": \"b.txt\"}\n",
+        )
+        .unwrap();
+        let jobs = parse_manifest(&manifest).unwrap();
+
+        std::fs::write(&manifest, "{\"audio\": \"a.wav\"}\n").unwrap();
+        let missing = parse_manifest(&manifest).unwrap_err().to_string();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].audio, dir.join("a.wav"));
+        assert_eq!(jobs[0].reference, "inline text");
+        assert_eq!(jobs[1].audio, PathBuf::from("/abs/b.wav"));
+        assert_eq!(jobs[1].reference, "from a file\n");
+        assert!(missing.contains(":1: set exactly one of"));
+    }
+
+    #[test]
+    fn aggregate_sums_counts_across_scored_files() {
+        let scored = |reference_len, substitutions| FileScore {
+            audio: PathBuf::from("a.wav"),
+            request_id: None,
+            wer: None,
+            cer: None,
+            words: Some(ErrorCounts {
+                reference_len,
+                substitutions,
+                insertions: 0,
+                deletions: 0,
+            }),
+            characters: Some(ErrorCounts::default()),
+            reference: None,
+            hypothesis: None,
+            error: None,
+        };
+        let failed = FileScore {
+            words: None,
+            characters: None,
+            error: Some("timeout".to_string()),
+            ..scored(0, 0)
+        };
+
+        let total = aggregate(&[scored(10, 1), scored(30, 1), failed]);
+        assert_eq!((total.files, total.failed), (2, 1));
+        assert_eq!(total.words.reference_len, 40);
+        assert!((total.wer - 0.05).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,537 @@
+use bytes::Bytes;
+use std::io::Read;
+use std::time::Duration;
+use symphonia::core::io::ReadOnlySource;
+use symphonia::core::probe::Hint;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::audio::decode_media_source;
+use crate::protocol::{StreamError, StreamResult};
+
+/// Number of fetched chunks buffered ahead of the decoder.
+const FETCH_BUFFER_CHUNKS: usize = 64;
+
+/// Most times a progressive source is reopened over a whole session, so a
+/// server that keeps closing the connection is not refetched forever.
+const MAX_SOURCE_RESTARTS: u32 = 100;
+
+/// How to retry the audio source when it drops, independently of the
+/// Deepgram connections.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SourceRetry {
+    pub(crate) attempts: u32,
+    pub(crate) backoff: Duration,
+}
+
+impl SourceRetry {
+    /// Delay before retry number `attempt` (starting at 1), doubling each time.
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
+
+/// Blocking reader over chunks fetched by an async task, so symphonia can
+/// decode a network stream on a blocking thread.
+struct ChannelReader {
+    rx: mpsc::Receiver<Bytes>,
+    current: Bytes,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.current.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.current.len());
+        buf[..len].copy_from_slice(&self.current.split_to(len));
+        Ok(len)
+    }
+}
+
+/// Symphonia format hint from a MIME type such as `audio/mpeg`.
+fn hint_from_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    match mime.as_str() {
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => Some("aac"),
+        "audio/ogg" | "application/ogg" | "audio/opus" => Some("ogg"),
+        "audio/flac" | "audio/x-flac" => Some("flac"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        "audio/webm" | "video/webm" => Some("webm"),
+        _ => None,
+    }
+}
+
+fn url_extension(url: &url::Url) -> Option<String> {
+    let name = url.path_segments()?.next_back()?;
+    let (_, ext) = name.rsplit_once('.')?;
+    Some(ext.to_ascii_lowercase())
+}
+
+fn is_hls(url: &url::Url, content_type: Option<&str>) -> bool {
+    url_extension(url).as_deref() == Some("m3u8")
+        || content_type.is_some_and(|content_type| {
+            let content_type = content_type.to_ascii_lowercase();
+            content_type.contains("mpegurl")
+        })
+}
+
+/// The parts of an HLS playlist needed to follow a live audio stream.
+#[derive(Debug, Default, PartialEq)]
+struct Playlist {
+    /// Variant stream URIs, present only in master playlists.
+    variants: Vec<String>,
+    media_sequence: u64,
+    target_duration: f64,
+    segments: Vec<String>,
+    ended: bool,
+}
+
+fn parse_playlist(text: &str) -> Result<Playlist, StreamError> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    if lines.next() != Some("#EXTM3U") {
+        return Err("Not an HLS playlist (missing #EXTM3U)".into());
+    }
+
+    let mut playlist = Playlist {
+        target_duration: 6.0,
+        ..Default::default()
+    };
+    let mut next_is_variant = false;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            playlist.media_sequence = value.parse()?;
+        } else if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            playlist.target_duration = value.parse()?;
+        } else if line.starts_with("#EXT-X-STREAM-INF") {
+            next_is_variant = true;
+        } else if line == "#EXT-X-ENDLIST" {
+            playlist.ended = true;
+        } else if line.starts_with("#EXT-X-MAP") {
+            return Err("HLS streams with fragmented MP4 segments are not supported".into());
+        } else if !line.starts_with('#') {
+            if next_is_variant {
+                playlist.variants.push(line.to_string());
+                next_is_variant = false;
+            } else {
+                playlist.segments.push(line.to_string());
+            }
+        }
+    }
+
+    Ok(playlist)
+}
+
+/// Fetch a URL and stream its body into `chunk_tx` until the body ends.
+/// Returns `Ok(true)` if the receiver has gone away.
+async fn pump_body(
+    response: reqwest::Response,
+    chunk_tx: &mpsc::Sender<Bytes>,
+) -> Result<bool, StreamError> {
+    let mut response = response;
+    while let Some(chunk) = response.chunk().await? {
+        if chunk_tx.send(chunk).await.is_err() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Whether a progressive response is a live broadcast rather than a file.
+/// Icecast and SHOUTcast announce themselves with `icy-*` (or `ice-*`)
+/// headers; anything else is treated as a file that ends when its body does.
+fn is_live_stream(headers: &reqwest::header::HeaderMap) -> bool {
+    headers.keys().any(|name| {
+        let name = name.as_str();
+        name.starts_with("icy-") || name.starts_with("ice-")
+    })
+}
+
+async fn get(client: &reqwest::Client, url: &url::Url) -> Result<reqwest::Response, StreamError> {
+    let response = client.get(url.clone()).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {} fetching {}", status, url).into());
+    }
+    Ok(response)
+}
+
+/// Follow a progressive HTTP or Icecast stream, reconnecting whenever the
+/// connection drops. A file is resumed where it stopped and finishes when its
+/// body ends; a live stream is rejoined at its live edge, including after a
+/// clean end of body.
+async fn fetch_progressive(
+    client: reqwest::Client,
+    url: url::Url,
+    first: reqwest::Response,
+    chunk_tx: mpsc::Sender<Bytes>,
+    retry: SourceRetry,
+) -> StreamResult {
+    let live = is_live_stream(first.headers());
+    let total_len = first.content_length();
+    let mut received = 0u64;
+    let mut response = Some(first);
+    let mut failures = 0;
+    let mut restarts = 0;
+    loop {
+        let mut current = match response.take() {
+            Some(response) => response,
+            None => {
+                let mut request = client.get(url.clone());
+                if !live {
+                    request = request.header(reqwest::header::RANGE, format!("bytes={received}-"));
+                }
+                match request
+                    .send()
+                    .await
+                    .map_err(StreamError::from)
+                    .and_then(|r| {
+                        if r.status().is_success() {
+                            Ok(r)
+                        } else {
+                            Err(format!("HTTP {} fetching {}", r.status(), url).into())
+                        }
+                    }) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        failures += 1;
+                        if failures > retry.attempts {
+                            return Err(format!(
+                                "Audio source failed after {} reconnect attempts: {}",
+                                retry.attempts, e
+                            )
+                            .into());
+                        }
+                        eprintln!(
+                            "Audio source reconnect attempt {}/{} failed: {}",
+                            failures, retry.attempts, e
+                        );
+                        tokio::time::sleep(retry.delay(failures)).await;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        // A server that ignores the Range header resends the file from the start.
+        let mut skip = if !live && current.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            received
+        } else {
+            0
+        };
+        let result: Result<bool, StreamError> = async {
+            while let Some(mut chunk) = current.chunk().await? {
+                if skip > 0 {
+                    let skipped = skip.min(chunk.len() as u64);
+                    skip -= skipped;
+                    let _ = chunk.split_to(skipped as usize);
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                }
+                received += chunk.len() as u64;
+                if chunk_tx.send(chunk).await.is_err() {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        .await;
+
+        match result {
+            Ok(true) => return Ok(()),
+            Ok(false) if !live && total_len.is_none_or(|len| received >= len) => return Ok(()),
+            Ok(false) => eprintln!("Audio source ended, reconnecting..."),
+            Err(e) => eprintln!("Audio source error: {}, reconnecting...", e),
+        }
+        restarts += 1;
+        if restarts > MAX_SOURCE_RESTARTS {
+            return Err(format!(
+                "Audio source dropped {} times; giving up",
+                MAX_SOURCE_RESTARTS
+            )
+            .into());
+        }
+        failures = 0;
+        tokio::time::sleep(retry.backoff).await;
+    }
+}
+
+/// Follow an HLS media playlist, appending each new segment to the stream
+/// until the playlist ends.
+async fn fetch_hls(
+    client: reqwest::Client,
+    playlist_url: url::Url,
+    chunk_tx: mpsc::Sender<Bytes>,
+    retry: SourceRetry,
+) -> StreamResult {
+    let mut next_sequence: Option<u64> = None;
+    let mut failures = 0;
+    loop {
+        let playlist = match fetch_playlist(&client, &playlist_url).await {
+            Ok((_, playlist)) => playlist,
+            Err(e) => {
+                failures += 1;
+                if failures > retry.attempts {
+                    return Err(format!(
+                        "HLS playlist failed after {} reconnect attempts: {}",
+                        retry.attempts, e
+                    )
+                    .into());
+                }
+                eprintln!(
+                    "HLS playlist reload attempt {}/{} failed: {}",
+                    failures, retry.attempts, e
+                );
+                tokio::time::sleep(retry.delay(failures)).await;
+                continue;
+            }
+        };
+        failures = 0;
+
+        for (idx, segment) in playlist.segments.iter().enumerate() {
+            let sequence = playlist.media_sequence + idx as u64;
+            if next_sequence.is_some_and(|next| sequence < next) {
+                continue;
+            }
+            next_sequence = Some(sequence + 1);
+
+            let segment_url = playlist_url.join(segment)?;
+            match get(&client, &segment_url).await {
+                Ok(response) => match pump_body(response, &chunk_tx).await {
+                    Ok(true) => return Ok(()),
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Skipping HLS segment {}: {}", sequence, e),
+                },
+                Err(e) => eprintln!("Skipping HLS segment {}: {}", sequence, e),
+            }
+        }
+
+        if playlist.ended {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs_f64(playlist.target_duration / 2.0)).await;
+    }
+}
+
+/// Fetch a playlist, following a master playlist to its first variant.
+async fn fetch_playlist(
+    client: &reqwest::Client,
+    url: &url::Url,
+) -> Result<(url::Url, Playlist), StreamError> {
+    let playlist = parse_playlist(&get(client, url).await?.text().await?)?;
+    match playlist.variants.first() {
+        Some(variant) => {
+            let variant_url = url.join(variant)?;
+            let playlist = parse_playlist(&get(client, &variant_url).await?.text().await?)?;
+            Ok((variant_url, playlist))
+        }
+        None => Ok((url.clone(), playlist)),
+    }
+}
+
+/// Stream audio from an HTTP(S), Icecast, or HLS URL: fetch it progressively,
+/// decode it to linear16, and forward the audio to `tx`.
+pub(crate) async fn stream_url(
+    url: String,
+    retry: SourceRetry,
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    config_tx: oneshot::Sender<(u32, u16)>,
+    ready_rx: oneshot::Receiver<()>,
+    fast_mode: bool,
+) -> StreamResult {
+    let url = url::Url::parse(&url)?;
+    let client = reqwest::Client::new();
+    let (chunk_tx, chunk_rx) = mpsc::channel::<Bytes>(FETCH_BUFFER_CHUNKS);
+
+    // Icecast servers only interleave stream metadata when asked with
+    // `Icy-MetaData: 1`, so the body is plain audio.
+    let first = get(&client, &url).await?;
+    let content_type = first
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let mut hint = Hint::new();
+    let fetch_task = if is_hls(&url, content_type.as_deref()) {
+        let text = first.text().await?;
+        let mut playlist_url = url.clone();
+        let mut playlist = parse_playlist(&text)?;
+        if !playlist.variants.is_empty() {
+            (playlist_url, playlist) = fetch_playlist(&client, &url).await?;
+        }
+        if let Some(segment) = playlist.segments.first() {
+            let segment_url = playlist_url.join(segment)?;
+            match url_extension(&segment_url).as_deref() {
+                Some("ts") => {
+                    return Err("HLS streams with MPEG-TS segments are not supported; \
+                                use a stream with AAC or MP3 segments"
+                        .into());
+                }
+                Some(ext) => {
+                    hint.with_extension(ext);
+                }
+                None => {}
+            }
+        }
+        println!("Source:  HLS playlist {}", playlist_url);
+        tokio::spawn(fetch_hls(client, playlist_url, chunk_tx, retry))
+    } else {
+        if let Some(ext) = content_type.as_deref().and_then(hint_from_content_type) {
+            hint.with_extension(ext);
+        } else if let Some(ext) = url_extension(&url) {
+            hint.with_extension(&ext);
+        }
+        println!(
+            "Source:  {} ({})",
+            url,
+            content_type.as_deref().unwrap_or("unknown content type")
+        );
+        tokio::spawn(fetch_progressive(client, url, first, chunk_tx, retry))
+    };
+
+    let reader = ChannelReader {
+        rx: chunk_rx,
+        current: Bytes::new(),
+    };
+    let decode_result = tokio::task::spawn_blocking(move || {
+        decode_media_source(
+            Box::new(ReadOnlySource::new(reader)),
+            &hint,
+            tx,
+            config_tx,
+            ready_rx,
+            fast_mode,
+        )
+    })
+    .await;
+
+    fetch_task.abort();
+    if let Ok(Err(e)) = fetch_task.await {
+        return Err(e);
+    }
+    decode_result?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChannelReader, Playlist, SourceRetry, fetch_progressive, hint_from_content_type, is_hls,
+        is_live_stream, parse_playlist,
+    };
+    use bytes::Bytes;
+    use std::io::Read;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn parses_master_and_media_playlists() {
+        let master = parse_playlist(
+            "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=64000,CODECS=\"mp4a.40.2\"\naudio/64k.m3u8\n",
+        )
+        .unwrap();
+        assert_eq!(master.variants, vec!["audio/64k.m3u8"]);
+
+        let media = parse_playlist(
+            "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:120\n\
+             #EXTINF:4.0,\nseg120.aac\n#EXTINF:4.0,\nseg121.aac\n#EXT-X-ENDLIST\n",
+        )
+        .unwrap();
+        assert_eq!(
+            media,
+            Playlist {
+                variants: vec![],
+                media_sequence: 120,
+                target_duration: 4.0,
+                segments: vec!["seg120.aac".to_string(), "seg121.aac".to_string()],
+                ended: true,
+            }
+        );
+
+        assert!(parse_playlist("not a playlist").is_err());
+    }
+
+    #[test]
+    fn detects_source_format_from_url_and_content_type() {
+        let hls = url::Url::parse("https://example.com/live/index.m3u8?token=1").unwrap();
+        let radio = url::Url::parse("https://example.com/radio").unwrap();
+        assert!(is_hls(&hls, None));
+        assert!(is_hls(&radio, Some("application/vnd.apple.mpegurl")));
+        assert!(!is_hls(&radio, Some("audio/mpeg")));
+
+        assert_eq!(hint_from_content_type("audio/mpeg"), Some("mp3"));
+        assert_eq!(hint_from_content_type("audio/aacp; charset=x"), Some("aac"));
+        assert_eq!(hint_from_content_type("text/html"), None);
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("content-type", "audio/mpeg".parse().unwrap());
+        assert!(!is_live_stream(&headers));
+        headers.insert("icy-name", "Radio".parse().unwrap());
+        assert!(is_live_stream(&headers));
+    }
+
+    #[test]
+    fn channel_reader_reads_across_chunks_until_sender_closes() {
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        tx.try_send(Bytes::from_static(b"hel")).unwrap();
+        tx.try_send(Bytes::from_static(b"lo")).unwrap();
+        drop(tx);
+
+        let mut reader = ChannelReader {
+            rx,
+            current: Bytes::new(),
+        };
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+        assert_eq!(output, "hello");
+    }
+
+    #[tokio::test]
+    async fn chunked_file_finishes_at_end_of_body() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url =
+            url::Url::parse(&format!("http://{}/a.mp3", listener.local_addr().unwrap())).unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let server_connections = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                server_connections.fetch_add(1, Ordering::SeqCst);
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Type: audio/mpeg\r\n\
+                          Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+                          5\r\nhello\r\n0\r\n\r\n",
+                    )
+                    .await;
+            }
+        });
+
+        let client = reqwest::Client::new();
+        let first = client.get(url.clone()).send().await.unwrap();
+        let (chunk_tx, mut chunk_rx) = tokio::sync::mpsc::channel(8);
+        let retry = SourceRetry {
+            attempts: 3,
+            backoff: Duration::from_millis(10),
+        };
+        fetch_progressive(client, url, first, chunk_tx, retry)
+            .await
+            .unwrap();
+
+        let mut body = Vec::new();
+        while let Some(chunk) = chunk_rx.recv().await {
+            body.extend_from_slice(&chunk);
+        }
+        assert_eq!(body, b"hello");
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const PERCENTILES: [f64; 4] = [50.0, 90.0, 95.0, 99.0];
+
+/// Latency measurements for one streaming connection.
+///
+/// Time to first transcript runs from the first audio chunk sent to the first
+/// `Results` message with a non-empty transcript. Lag is how far the audio
+/// sent so far is ahead of the end (`start + duration`) of each final result
+/// when it arrives, in seconds of audio.
+#[derive(Debug)]
+pub(crate) struct LatencyTracker {
+    connection: usize,
+    audio_started: Option<Instant>,
+    first_transcript: Option<Duration>,
+    lags: Vec<f64>,
+}
+
+impl LatencyTracker {
+    pub(crate) fn new(connection: usize) -> Self {
+        LatencyTracker {
+            connection,
+            audio_started: None,
+            first_transcript: None,
+            lags: Vec::new(),
+        }
+    }
+
+    pub(crate) fn audio_sent(&mut self) {
+        self.audio_started.get_or_insert_with(Instant::now);
+    }
+
+    pub(crate) fn result_received(
+        &mut self,
+        has_transcript: bool,
+        is_final: bool,
+        result_end: f64,
+        audio_sent_seconds: f64,
+    ) {
+        if has_transcript
+            && self.first_transcript.is_none()
+            && let Some(started) = self.audio_started
+        {
+            self.first_transcript = Some(started.elapsed());
+        }
+        if is_final {
+            self.lags.push(audio_sent_seconds - result_end);
+        }
+    }
+}
+
+/// Collects every connection's measurements for the end-of-run report.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LatencyCollector {
+    trackers: Arc<Mutex<Vec<LatencyTracker>>>,
+    csv: Option<PathBuf>,
+}
+
+impl LatencyCollector {
+    pub(crate) fn new(csv: Option<PathBuf>) -> Self {
+        LatencyCollector {
+            trackers: Arc::default(),
+            csv,
+        }
+    }
+
+    pub(crate) fn push(&self, tracker: LatencyTracker) {
+        self.trackers.lock().unwrap().push(tracker);
+    }
+
+    /// Print the latency table and write the CSV export, if requested.
+    pub(crate) fn report(&self) -> std::io::Result<()> {
+        let mut trackers = std::mem::take(&mut *self.trackers.lock().unwrap());
+        trackers.sort_by_key(|tracker| tracker.connection);
+        let rows = summary_rows(&trackers);
+
+        print!("\n{}", render_table(&rows));
+        if let Some(path) = &self.csv {
+            write_csv(path, &rows)?;
+            println!("Latency summary written to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct SummaryRow {
+    label: String,
+    /// Median time to first transcript over the row's connections, in seconds.
+    first_transcript: Option<f64>,
+    finals: usize,
+    /// Lag percentiles in `PERCENTILES` order, followed by the maximum.
+    lag: Option<[f64; 5]>,
+}
+
+/// One row per connection, plus an `all` row over every connection when
+/// there is more than one.
+fn summary_rows(trackers: &[LatencyTracker]) -> Vec<SummaryRow> {
+    let row = |label: String, mut first_transcripts: Vec<f64>, mut lags: Vec<f64>| {
+        first_transcripts.sort_by(f64::total_cmp);
+        lags.sort_by(f64::total_cmp);
+        SummaryRow {
+            label,
+            first_transcript: percentile(&first_transcripts, 50.0),
+            finals: lags.len(),
+            lag: lags.last().map(|max| {
+                let p = |q| percentile(&lags, q).unwrap_or_default();
+                [
+                    p(PERCENTILES[0]),
+                    p(PERCENTILES[1]),
+                    p(PERCENTILES[2]),
+                    p(PERCENTILES[3]),
+                    *max,
+                ]
+            }),
+        }
+    };
+
+    let mut rows: Vec<SummaryRow> = trackers
+        .iter()
+        .map(|tracker| {
+            row(
+                tracker.connection.to_string(),
+                tracker
+                    .first_transcript
+                    .iter()
+                    .map(Duration::as_secs_f64)
+                    .collect(),
+                tracker.lags.clone(),
+            )
+        })
+        .collect();
+    if trackers.len() > 1 {
+        rows.push(row(
+            "all".to_string(),
+            trackers
+                .iter()
+                .filter_map(|tracker| tracker.first_transcript)
+                .map(|duration| duration.as_secs_f64())
+                .collect(),
+            trackers
+                .iter()
+                .flat_map(|tracker| tracker.lags.clone())
+                .collect(),
+        ));
+    }
+    rows
+}
+
+/// Nearest-rank percentile of already sorted values.
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn millis(seconds: Option<f64>) -> String {
+    seconds
+        .map(|seconds| format!("{:.0}", seconds * 1000.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn render_table(rows: &[SummaryRow]) -> String {
+    let mut out = String::from("=== Latency (ms) ===\n");
+    let _ = writeln!(
+        out,
+        "{:<6} {:>10} {:>7} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "Conn", "First", "Finals", "Lag p50", "Lag p90", "Lag p95", "Lag p99", "Lag max"
+    );
+    for row in rows {
+        let lag = |i: usize| millis(row.lag.map(|lag| lag[i]));
+        let _ = writeln!(
+            out,
+            "{:<6} {:>10} {:>7} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            row.label,
+            millis(row.first_transcript),
+            row.finals,
+            lag(0),
+            lag(1),
+            lag(2),
+            lag(3),
+            lag(4)
+        );
+    }
+    out.push_str(
+        "First = time to first transcript; Lag = audio sent minus end of each final result\n",
+    );
+    out
+}
+
+fn write_csv(path: &Path, rows: &[SummaryRow]) -> std::io::Result<()> {
+    let mut csv = String::from(
+        "connection,first_transcript_ms,finals,lag_p50_ms,lag_p90_ms,lag_p95_ms,lag_p99_ms,lag_max_ms\n",
+    );
+    for row in rows {
+        let optional = |value: Option<f64>| {
+            value
+                .map(|seconds| format!("{:.1}", seconds * 1000.0))
+                .unwrap_or_default()
+        };
+        let _ = write!(
+            csv,
+            "{},{},{}",
+            row.label,
+            optional(row.first_transcript),
+            row.finals
+        );
+        for i in 0..5 {
+            let _ = write!(csv, ",{}", optional(row.lag.map(|lag| lag[i])));
+        }
+        csv.push('\n');
+    }
+    std::fs::write(path, csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LatencyTracker, percentile, summary_rows};
+    use std::time::Duration;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&values, 50.0), Some(5.0));
+        assert_eq!(percentile(&values, 90.0), Some(9.0));
+        assert_eq!(percentile(&values, 99.0), Some(10.0));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn summarizes_each_connection_and_all_connections() {
+        let mut first = LatencyTracker::new(1);
+        first.first_transcript = Some(Duration::from_millis(400));
+        for (result_end, sent) in [(1.0, 1.2), (2.0, 2.5), (3.0, 3.3)] {
+            first.result_received(true, true, result_end, sent);
+        }
+        let mut second = LatencyTracker::new(2);
+        second.result_received(true, false, 0.5, 1.0);
+
+        let rows = summary_rows(&[first, second]);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].finals, 3);
+        assert_eq!(rows[0].first_transcript, Some(0.4));
+        let lag = rows[0].lag.unwrap();
+        assert!((lag[0] - 0.3).abs() < 1e-9 && (lag[4] - 0.5).abs() < 1e-9);
+        // Audio never started on connection 2, so it has no first transcript time.
+        assert_eq!(
+            (rows[1].finals, rows[1].first_transcript, rows[1].lag),
+            (0, None, None)
+        );
+        assert_eq!((rows[2].label.as_str(), rows[2].finals), ("all", 3));
+    }
+}
@@ -1,23 +1,55 @@
 mod audio;
+mod batch;
+mod callback;
 mod cli;
+mod controls;
 mod deepgram;
+mod devices;
+mod encode;
+mod eval;
+mod http_source;
+mod latency;
 mod models;
+mod profile;
 mod protocol;
+mod query;
+mod record;
+mod resample;
+mod serve;
 mod stream;
+mod subtitles;
 mod transcribe;
+mod transcript_log;
+mod wer;
 
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use dotenv::dotenv;
+use std::collections::BTreeMap;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
 
-use crate::audio::{AudioCapture, AudioFileReader, connection_prefix, start_audio_fanout};
+use crate::audio::{
+    AudioCapture, AudioFileReader, RawAudioFormat, bytes_per_sample, start_audio_fanout,
+    start_channel_split, stream_stdin,
+};
 use crate::cli::{Cli, Commands};
-use crate::deepgram::run_deepgram_client;
+use crate::controls::{CONTROLS_HELP, StreamControl, spawn_keyboard_controls};
+use crate::deepgram::{
+    ClientInputs, build_stream_url, handshake_status, print_stream_dry_run, run_deepgram_client,
+};
+use crate::devices::InputDeviceArgs;
+use crate::encode::{AudioEncoder, start_audio_encoding};
+use crate::http_source::{SourceRetry, stream_url};
+use crate::profile::ProfileCommand;
 use crate::protocol::{DeepgramClientConfig, StreamResult};
-use crate::stream::StreamSource;
+use crate::record::{SessionRecord, WavFormat, start_recording};
+use crate::resample::{AudioConverter, start_audio_conversion};
+use crate::serve::TranscriptServer;
+use crate::stream::{StreamOptions, StreamSource};
+use crate::subtitles::SubtitleFormat;
 
 fn hosted_deepgram_endpoint(endpoint: Option<&str>) -> bool {
     let Some(endpoint) = endpoint else {
@@ -45,9 +77,73 @@ fn api_key_for_endpoint(
 
 #[cfg(test)]
 mod tests {
-    use super::hosted_deepgram_endpoint;
-    use crate::audio::{repair_wav_header, wav_data_len};
+    use super::{channel_labels, connection_summary, hosted_deepgram_endpoint};
+    use crate::audio::{
+        RawAudioFormat, repair_wav_header, start_channel_split, stream_raw, wav_data_len,
+    };
     use crate::protocol::DeepgramResponse;
+    use std::collections::BTreeMap;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn connection_summary_groups_failures_by_status() {
+        let mut failures = BTreeMap::new();
+        assert_eq!(
+            connection_summary(4, &failures),
+            "Connections: 4 finished, 0 failed"
+        );
+        failures.insert("HTTP 429".to_string(), 2);
+        failures.insert("other error".to_string(), 1);
+        assert_eq!(
+            connection_summary(7, &failures),
+            "Connections: 7 finished, 3 failed (HTTP 429: 2, other error: 1)"
+        );
+    }
+
+    #[tokio::test]
+    async fn splits_interleaved_channels_across_chunk_boundaries() {
+        let (source_tx, source_rx) = mpsc::unbounded_channel();
+        let (mut receivers, split_task) = start_channel_split(source_rx, 2, 2);
+
+        // Stereo i16 frames: left samples are 1, 2, 3 and right samples are -1, -2, -3,
+        // sent with the second frame cut off mid-sample.
+        let frames: Vec<u8> = [1i16, -1, 2, -2, 3, -3]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        source_tx.send(frames[..5].to_vec()).unwrap();
+        source_tx.send(frames[5..].to_vec()).unwrap();
+        drop(source_tx);
+        split_task.await.unwrap();
+
+        let mut channel = |idx: usize| {
+            let mut samples = Vec::new();
+            while let Ok(chunk) = receivers[idx].try_recv() {
+                samples.extend(
+                    chunk
+                        .chunks_exact(2)
+                        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]])),
+                );
+            }
+            samples
+        };
+        assert_eq!(channel(0), vec![1, 2, 3]);
+        assert_eq!(channel(1), vec![-1, -2, -3]);
+    }
+
+    #[test]
+    fn channel_labels_default_or_must_name_every_channel() {
+        assert_eq!(
+            channel_labels(None, 2).unwrap(),
+            vec!["channel 1", "channel 2"]
+        );
+        assert_eq!(
+            channel_labels(Some("agent, customer"), 2).unwrap(),
+            vec!["agent", "customer"]
+        );
+        assert!(channel_labels(Some("agent"), 2).is_err());
+        assert!(channel_labels(Some("agent,"), 2).is_err());
+    }
 
     #[test]
     fn default_and_hosted_endpoints_require_deepgram_auth() {
@@ -81,6 +177,76 @@ mod tests {
         assert_eq!(utterance_end.channel.unwrap(), serde_json::json!([0, 1]));
     }
 
+    #[test]
+    fn transcribe_accepts_exactly_one_of_file_or_url() {
+        use crate::cli::Cli;
+        use clap::Parser;
+
+        assert!(Cli::try_parse_from(["dg-stt", "transcribe", "--file", "a.wav"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["dg-stt", "transcribe", "--url", "https://example.com/a.wav"])
+                .is_ok()
+        );
+        assert!(Cli::try_parse_from(["dg-stt", "transcribe"]).is_err());
+        assert!(
+            Cli::try_parse_from([
+                "dg-stt",
+                "transcribe",
+                "--file",
+                "a.wav",
+                "--url",
+                "https://example.com/a.wav",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn stdin_raw_mode_requires_declared_format() {
+        use crate::cli::Cli;
+        use clap::Parser;
+
+        assert!(Cli::try_parse_from(["dg-stt", "stream", "stdin"]).is_ok());
+        assert!(Cli::try_parse_from(["dg-stt", "stream", "stdin", "--raw"]).is_err());
+        assert!(
+            Cli::try_parse_from([
+                "dg-stt",
+                "stream",
+                "stdin",
+                "--raw",
+                "--encoding",
+                "mulaw",
+                "--sample-rate",
+                "8000",
+            ])
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn raw_audio_is_forwarded_unchanged() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let (config_tx, config_rx) = tokio::sync::oneshot::channel();
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+        ready_tx.send(()).unwrap();
+
+        let input: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+        let format = RawAudioFormat {
+            encoding: "mulaw".to_string(),
+            sample_rate: 8000,
+            channels: 1,
+        };
+        stream_raw(&input[..], &format, tx, config_tx, ready_rx, true).unwrap();
+
+        assert_eq!(config_rx.blocking_recv().unwrap(), (8000, 1));
+        let mut output = Vec::new();
+        while let Ok(chunk) = rx.try_recv() {
+            assert!(chunk.len() <= 800);
+            output.extend(chunk);
+        }
+        assert_eq!(output, input);
+    }
+
     #[test]
     fn wav_data_len_uses_bytes_when_declared_chunk_size_is_zero() {
         let mut wav = b"RIFF\0\0\0\0WAVEdata\0\0\0\0".to_vec();
@@ -101,40 +267,283 @@ mod tests {
 
 async fn wait_for_deepgram_tasks(
     tasks: Vec<JoinHandle<StreamResult>>,
-    connection_count: usize,
+    prefixes: Vec<String>,
     success_message: &'static str,
 ) {
+    let mut finished = 0;
+    let mut failures: BTreeMap<String, usize> = BTreeMap::new();
     for (idx, task) in tasks.into_iter().enumerate() {
-        let connection_id = idx + 1;
         match task.await {
-            Ok(Ok(())) => println!(
-                "{}{}",
-                connection_prefix(connection_id, connection_count),
-                success_message
-            ),
-            Ok(Err(e)) => eprintln!(
-                "{}Deepgram client error: {}",
-                connection_prefix(connection_id, connection_count),
-                e
-            ),
-            Err(e) => eprintln!(
-                "{}Deepgram task join error: {}",
-                connection_prefix(connection_id, connection_count),
-                e
-            ),
+            Ok(Ok(())) => {
+                finished += 1;
+                println!("{}{}", prefixes[idx], success_message)
+            }
+            Ok(Err(e)) => {
+                let reason = match handshake_status(&e) {
+                    Some(status) => format!("HTTP {status}"),
+                    None => "other error".to_string(),
+                };
+                *failures.entry(reason).or_default() += 1;
+                eprintln!("{}Deepgram client error: {}", prefixes[idx], e)
+            }
+            Err(e) => {
+                *failures.entry("other error".to_string()).or_default() += 1;
+                eprintln!("{}Deepgram task join error: {}", prefixes[idx], e)
+            }
+        }
+    }
+
+    if prefixes.len() > 1 {
+        println!("{}", connection_summary(finished, &failures));
+    }
+}
+
+fn connection_prefixes(config: &DeepgramClientConfig, connection_count: usize) -> Vec<String> {
+    (1..=connection_count)
+        .map(|connection_id| config.connection_prefix(connection_id, connection_count))
+        .collect()
+}
+
+/// Apply `--target-sample-rate` and `--mono` to linear16 source audio.
+/// Returns the audio to send and its format, which the query parameters
+/// follow from then on.
+fn start_format_conversion(
+    audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    client_config: &mut DeepgramClientConfig,
+    (sample_rate, channels): (u32, u16),
+    target_sample_rate: Option<u32>,
+    mono: bool,
+) -> (mpsc::UnboundedReceiver<Vec<u8>>, u32, u16) {
+    let converter = AudioConverter::new(sample_rate, channels, target_sample_rate, mono);
+    if converter.is_identity() {
+        return (audio_rx, sample_rate, channels);
+    }
+
+    let converted_rate = target_sample_rate.unwrap_or(sample_rate);
+    let converted_channels = if mono { 1 } else { channels };
+    println!(
+        "Converting audio: {sample_rate} Hz, {channels} channel(s) -> {converted_rate} Hz, {converted_channels} channel(s)"
+    );
+    client_config.sample_rate_override = None;
+    client_config.channels_override = None;
+    (
+        start_audio_conversion(audio_rx, converter),
+        converted_rate,
+        converted_channels,
+    )
+}
+
+/// Encode linear16 audio as `--encoding` before it is sent.
+fn start_encoding(
+    audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    client_config: &DeepgramClientConfig,
+    sample_rate: u32,
+    channels: u16,
+) -> Result<mpsc::UnboundedReceiver<Vec<u8>>, Box<dyn std::error::Error>> {
+    let Some(encoding) = client_config.encoding.as_deref() else {
+        return Ok(audio_rx);
+    };
+    let Some(encoder) = AudioEncoder::for_encoding(encoding, sample_rate, channels)? else {
+        return Ok(audio_rx);
+    };
+    if encoder.needs_stream_start()
+        && (client_config.reconnect.reconnect || client_config.ramp_up.is_some())
+    {
+        return Err(
+            "--encoding ogg-opus cannot be used with --reconnect or --ramp-up, \
+            which send audio from the middle of the stream; use --encoding opus instead"
+                .into(),
+        );
+    }
+
+    println!("Encoding audio as {encoding}");
+    Ok(start_audio_encoding(audio_rx, encoder))
+}
+
+/// The `--record` tee task and the sidecar the connections report to.
+type Recording = (JoinHandle<()>, SessionRecord);
+
+/// Audio ready for routing, and the `--record` tee if one was started.
+type RecordedAudio = (mpsc::UnboundedReceiver<Vec<u8>>, Option<Recording>);
+
+/// Encode the audio for sending and, with `--record`, tee it into a WAV file:
+/// the bytes as sent when a WAV file can hold them, otherwise (for Opus) the
+/// linear16 audio before it is encoded.
+fn start_encoding_and_recording(
+    audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    client_config: &DeepgramClientConfig,
+    (sample_rate, channels): (u32, u16),
+    raw_input: bool,
+    record: Option<&Path>,
+) -> Result<RecordedAudio, Box<dyn std::error::Error>> {
+    // Raw stdin already carries audio in --encoding.
+    let encode = |audio_rx| {
+        if raw_input {
+            Ok(audio_rx)
+        } else {
+            start_encoding(audio_rx, client_config, sample_rate, channels)
         }
+    };
+    let Some(path) = record else {
+        return Ok((encode(audio_rx)?, None));
+    };
+
+    let encoding = client_config.encoding.as_deref().unwrap_or("linear16");
+    if let Some(format) = WavFormat::for_encoding(encoding, sample_rate, channels) {
+        let (audio_rx, task, session) = start_recording(encode(audio_rx)?, path, format, None)?;
+        return Ok((audio_rx, Some((task, session))));
+    }
+    if raw_input {
+        return Err(format!(
+            "--record can store linear16, linear32, mulaw, or alaw audio from --raw, not {encoding}"
+        )
+        .into());
+    }
+    // Fail on an unsupported --encoding before creating the recording.
+    AudioEncoder::for_encoding(encoding, sample_rate, channels)?;
+    let format = WavFormat::for_encoding("linear16", sample_rate, channels)
+        .ok_or("linear16 is always recordable")?;
+    let (audio_rx, task, session) =
+        start_recording(audio_rx, path, format, Some(encoding.to_string()))?;
+    Ok((encode(audio_rx)?, Some((task, session))))
+}
+
+/// Point the `--record` sidecar at the streaming URL and let the connections
+/// report their request IDs to it.
+fn attach_recording(
+    client_config: &mut DeepgramClientConfig,
+    recording: Option<&Recording>,
+    sample_rate: u32,
+    connection_channels: u16,
+    split_channels: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some((_, session)) = recording {
+        session.set_url(
+            build_stream_url(client_config, sample_rate, connection_channels),
+            split_channels,
+        )?;
+        client_config.recording = Some(session.clone());
+    }
+    Ok(())
+}
+
+/// Start the `--serve` server and have the connections publish to it.
+async fn attach_server(
+    client_config: &mut DeepgramClientConfig,
+    serve: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(addr) = serve {
+        let (server, local_addr) = TranscriptServer::start(addr)
+            .await
+            .map_err(|e| format!("Failed to serve transcripts on {addr}: {e}"))?;
+        println!(
+            "Serving captions at http://{local_addr}/ (Server-Sent Events at /events, WebSocket at /ws)"
+        );
+        client_config.server = Some(server);
+    }
+    Ok(())
+}
+
+/// Wait for the `--record` tee to write the final WAV header and sidecar.
+async fn finish_recording(recording: Option<Recording>) {
+    if let Some((task, _)) = recording
+        && let Err(e) = task.await
+    {
+        eprintln!("Recording task join error: {}", e);
+    }
+}
+
+/// Per-connection audio receivers, the task feeding them, and the channel
+/// count each connection receives.
+type AudioRouting = (Vec<mpsc::UnboundedReceiver<Vec<u8>>>, JoinHandle<()>, u16);
+
+/// Route source audio to the connections: a copy to each of `--connections`,
+/// or with `--split-channels`, each channel as mono to its own connection.
+fn start_audio_routing(
+    audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    client_config: &mut DeepgramClientConfig,
+    connections: usize,
+    split_channels: bool,
+    labels: Option<&str>,
+    channels: u16,
+) -> Result<AudioRouting, Box<dyn std::error::Error>> {
+    if !split_channels {
+        let (receivers, fanout_task) = start_audio_fanout(audio_rx, connections);
+        return Ok((receivers, fanout_task, channels));
+    }
+
+    if channels < 2 {
+        return Err(format!(
+            "--split-channels needs multichannel audio, but the source has {channels} channel"
+        )
+        .into());
+    }
+    let sample_bytes = bytes_per_sample(client_config.encoding.as_deref().unwrap_or("linear16"))
+        .ok_or("--split-channels needs uncompressed audio (linear16, linear32, mulaw, or alaw)")?;
+    client_config.channel_labels = channel_labels(labels, channels)?;
+    client_config.channels_override = Some(1);
+
+    let (receivers, split_task) = start_channel_split(audio_rx, channels, sample_bytes as usize);
+    Ok((receivers, split_task, 1))
+}
+
+/// Labels from `--channel-labels`, or `channel 1`, `channel 2`, ... by default.
+fn channel_labels(
+    labels: Option<&str>,
+    channels: u16,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let Some(labels) = labels else {
+        return Ok((1..=channels)
+            .map(|channel| format!("channel {channel}"))
+            .collect());
+    };
+
+    let labels: Vec<String> = labels
+        .split(',')
+        .map(|label| label.trim().to_string())
+        .collect();
+    if labels.len() != channels as usize || labels.iter().any(String::is_empty) {
+        return Err(format!(
+            "--channel-labels must name all {channels} channels, e.g. \"agent,customer\""
+        )
+        .into());
+    }
+    Ok(labels)
+}
+
+/// One-line outcome of a multi-connection run, with failures grouped by the
+/// HTTP status that rejected the handshake.
+fn connection_summary(finished: usize, failures: &BTreeMap<String, usize>) -> String {
+    let failed: usize = failures.values().sum();
+    let mut summary = format!("Connections: {finished} finished, {failed} failed");
+    if failed > 0 {
+        let reasons: Vec<String> = failures
+            .iter()
+            .map(|(reason, count)| format!("{reason}: {count}"))
+            .collect();
+        summary.push_str(&format!(" ({})", reasons.join(", ")));
+    }
+    summary
+}
+
+/// Resolves once `--duration` has elapsed, or never if it is not set.
+async fn duration_elapsed(duration: Option<f64>) {
+    match duration {
+        Some(secs) => tokio::time::sleep(Duration::from_secs_f64(secs)).await,
+        None => std::future::pending().await,
     }
 }
 
-async fn wait_for_file_tasks(
+async fn wait_for_source_tasks(
+    source_name: &'static str,
     stream_task: JoinHandle<StreamResult>,
     fanout_task: JoinHandle<()>,
     deepgram_tasks: Vec<JoinHandle<StreamResult>>,
-    connection_count: usize,
+    prefixes: Vec<String>,
 ) {
     match stream_task.await {
         Ok(Ok(())) => {}
-        Ok(Err(e)) => eprintln!("File streaming error: {}", e),
+        Ok(Err(e)) => eprintln!("Error streaming {}: {}", source_name, e),
         Err(e) => eprintln!("Stream task join error: {}", e),
     }
 
@@ -146,47 +555,56 @@ async fn wait_for_file_tasks(
 
     wait_for_deepgram_tasks(
         deepgram_tasks,
-        connection_count,
+        prefixes,
         "Deepgram client finished successfully",
     )
     .await;
 }
 
+fn validate_stream_options(options: &StreamOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if options.connections == 0 {
+        return Err("--connections must be at least 1".into());
+    }
+
+    if let Some(path) = &options.subtitles
+        && SubtitleFormat::from_path(path).is_none()
+    {
+        return Err("--subtitles path must end in .srt or .vtt".into());
+    }
+
+    if options
+        .ramp_up
+        .is_some_and(|secs| !(secs >= 0.0 && secs.is_finite()))
+    {
+        return Err("--ramp-up must be a non-negative number of seconds".into());
+    }
+
+    if options
+        .duration
+        .is_some_and(|secs| !(secs > 0.0 && secs.is_finite()))
+    {
+        return Err("--duration must be a positive number of seconds".into());
+    }
+
+    if options.target_sample_rate == Some(0) {
+        return Err("--target-sample-rate must be a positive number of Hz".into());
+    }
+
+    Ok(())
+}
+
 async fn run_microphone_mode(
     api_key: Option<String>,
-    connections: usize,
-    callback: Option<String>,
-    silent: bool,
-    endpoint: Option<String>,
-    encoding: Option<String>,
-    sample_rate_override: Option<u32>,
-    channels_override: Option<u16>,
-    multichannel: bool,
-    diarize: bool,
-    detect_entities: bool,
-    interim_results: bool,
-    vad_events: bool,
-    punctuate: bool,
-    smart_format: bool,
-    sentiment: bool,
-    intents: bool,
-    topics: bool,
-    model: Option<String>,
-    version: Option<String>,
-    redact: Option<String>,
-    language: Option<String>,
-    endpointing: Option<u32>,
-    utterance_end: Option<u32>,
-    keyterm: Option<String>,
-    keywords: Option<String>,
+    input: InputDeviceArgs,
+    options: StreamOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if connections == 0 {
-        return Err("--connections must be at least 1".into());
-    }
+    validate_stream_options(&options)?;
+    let connections = options.connections;
+    let duration = options.duration;
 
     println!("Starting Deepgram real-time transcription from microphone...");
 
-    let audio_capture = AudioCapture::new()?;
+    let audio_capture = AudioCapture::new(&input)?;
     let sample_rate = audio_capture.config.sample_rate.0;
     let channels = audio_capture.config.channels;
 
@@ -195,60 +613,87 @@ async fn run_microphone_mode(
         sample_rate, channels
     );
 
-    let (audio_tx, audio_receivers, fanout_task) = start_audio_fanout(connections);
+    let split_channels = options.split_channels;
+    let labels = options.channel_labels.clone();
+    let (target_sample_rate, mono) = (options.target_sample_rate, options.mono);
+    let dry_run = options.dry_run;
+    let record = options.record.clone().filter(|_| !dry_run);
+    let serve = options.serve.clone();
+    let mut client_config = options.client_config(api_key);
+    let (audio_tx, audio_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let (audio_rx, sample_rate, channels) = start_format_conversion(
+        audio_rx,
+        &mut client_config,
+        (sample_rate, channels),
+        target_sample_rate,
+        mono,
+    );
+    let (audio_rx, recording) = start_encoding_and_recording(
+        audio_rx,
+        &client_config,
+        (sample_rate, channels),
+        false,
+        record.as_deref(),
+    )?;
+    let (audio_receivers, fanout_task, connection_channels) = start_audio_routing(
+        audio_rx,
+        &mut client_config,
+        connections,
+        split_channels,
+        labels.as_deref(),
+        channels,
+    )?;
+    let connections = audio_receivers.len();
+    if dry_run {
+        print_stream_dry_run(&client_config, sample_rate, connection_channels)?;
+        if connections > 1 {
+            println!("(each of the {connections} connections sends this request)");
+        }
+        return Ok(());
+    }
+    attach_recording(
+        &mut client_config,
+        recording.as_ref(),
+        sample_rate,
+        connection_channels,
+        split_channels,
+    )?;
+    attach_server(&mut client_config, serve.as_deref()).await?;
     let mut shutdown_senders = Vec::with_capacity(connections);
 
     let stream_handle = audio_capture.start_capture(audio_tx)?;
 
     println!("Listening for audio... Press Ctrl+C to stop.");
 
-    let client_config = DeepgramClientConfig {
-        api_key,
-        callback,
-        silent,
-        endpoint,
-        encoding,
-        sample_rate_override,
-        channels_override,
-        multichannel,
-        diarize,
-        detect_entities,
-        interim_results,
-        vad_events,
-        punctuate,
-        smart_format,
-        sentiment,
-        intents,
-        topics,
-        model,
-        version,
-        redact,
-        language,
-        endpointing,
-        utterance_end,
-        keyterm,
-        keywords,
-    };
-
     let mut deepgram_tasks = Vec::with_capacity(connections);
+    let mut control_senders = Vec::with_capacity(connections);
     for (idx, audio_rx) in audio_receivers.into_iter().enumerate() {
         let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>(1);
+        let (control_tx, control_rx) = mpsc::unbounded_channel::<StreamControl>();
         shutdown_senders.push(shutdown_tx);
+        control_senders.push(control_tx);
         deepgram_tasks.push(tokio::spawn(run_deepgram_client(
             client_config.clone(),
             idx + 1,
             connections,
             sample_rate,
-            channels,
-            audio_rx,
-            None,
-            shutdown_rx,
+            connection_channels,
+            ClientInputs {
+                audio_rx,
+                ready_tx: None,
+                shutdown_rx,
+                control_rx: Some(control_rx),
+            },
         )));
     }
 
+    if spawn_keyboard_controls(control_senders) {
+        println!("{CONTROLS_HELP}");
+    }
+
     let mut deepgram_tasks_future = Box::pin(wait_for_deepgram_tasks(
         deepgram_tasks,
-        connections,
+        connection_prefixes(&client_config, connections),
         "Deepgram client finished successfully",
     ));
 
@@ -261,111 +706,229 @@ async fn run_microphone_mode(
 
             deepgram_tasks_future.await;
         }
+        _ = duration_elapsed(duration) => {
+            println!("\nReached --duration, closing connections...");
+            for shutdown_tx in shutdown_senders {
+                let _ = shutdown_tx.send(()).await;
+            }
+
+            deepgram_tasks_future.await;
+        }
         _ = &mut deepgram_tasks_future => {}
     }
 
     drop(stream_handle);
     fanout_task.abort();
     let _ = fanout_task.await;
+    finish_recording(recording).await;
+
+    if let Some(latency) = &client_config.latency {
+        latency.report()?;
+    }
 
     Ok(())
 }
 
 async fn run_file_mode(
     api_key: Option<String>,
-    connections: usize,
     file_path: PathBuf,
     fast: bool,
-    callback: Option<String>,
-    silent: bool,
-    endpoint: Option<String>,
-    encoding: Option<String>,
-    sample_rate_override: Option<u32>,
-    channels_override: Option<u16>,
-    multichannel: bool,
-    diarize: bool,
-    detect_entities: bool,
-    interim_results: bool,
-    vad_events: bool,
-    punctuate: bool,
-    smart_format: bool,
-    sentiment: bool,
-    intents: bool,
-    topics: bool,
-    model: Option<String>,
-    version: Option<String>,
-    redact: Option<String>,
-    language: Option<String>,
-    endpointing: Option<u32>,
-    utterance_end: Option<u32>,
-    keyterm: Option<String>,
-    keywords: Option<String>,
+    looping: bool,
+    options: StreamOptions,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if connections == 0 {
-        return Err("--connections must be at least 1".into());
-    }
+    validate_stream_options(&options)?;
 
     println!("Starting Deepgram transcription from file...");
     println!("File: {}", file_path.display());
+    println!(
+        "Mode: {}{}",
+        if fast { "Fast" } else { "Real-time" },
+        if looping { ", looping" } else { "" }
+    );
+
+    let file_reader = AudioFileReader::new(file_path);
+    run_source_mode(
+        api_key,
+        options,
+        "audio file",
+        false,
+        move |audio_tx, config_tx, stream_ready_rx| {
+            tokio::spawn(async move {
+                file_reader
+                    .stream_file(audio_tx, config_tx, Some(stream_ready_rx), fast, looping)
+                    .await
+            })
+        },
+    )
+    .await
+}
+
+async fn run_stdin_mode(
+    api_key: Option<String>,
+    raw: bool,
+    fast: bool,
+    options: StreamOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    validate_stream_options(&options)?;
+
+    println!("Starting Deepgram transcription from stdin...");
     println!("Mode: {}", if fast { "Fast" } else { "Real-time" });
 
-    let (audio_tx, audio_receivers, fanout_task) = start_audio_fanout(connections);
-    let (config_tx, config_rx) = oneshot::channel::<(u32, u16)>();
-    let mut shutdown_senders = Vec::with_capacity(connections);
+    if raw
+        && (options.target_sample_rate.is_some() || options.mono)
+        && !options
+            .encoding
+            .as_deref()
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("linear16"))
+    {
+        return Err("--target-sample-rate and --mono need linear16 input with --raw".into());
+    }
 
-    let file_reader = AudioFileReader::new(file_path);
+    let raw_format = if raw {
+        // clap requires --encoding and --sample-rate alongside --raw.
+        Some(RawAudioFormat {
+            encoding: options.encoding.clone().unwrap_or_default(),
+            sample_rate: options.sample_rate.unwrap_or_default(),
+            channels: options.channels.unwrap_or(1),
+        })
+    } else {
+        None
+    };
+
+    run_source_mode(
+        api_key,
+        options,
+        "stdin",
+        raw,
+        move |audio_tx, config_tx, stream_ready_rx| {
+            // Reading stdin blocks, so it runs off the async worker threads.
+            tokio::task::spawn_blocking(move || {
+                stream_stdin(raw_format, audio_tx, config_tx, stream_ready_rx, fast)
+            })
+        },
+    )
+    .await
+}
+
+async fn run_url_mode(
+    api_key: Option<String>,
+    url: String,
+    retry: SourceRetry,
+    fast: bool,
+    options: StreamOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    validate_stream_options(&options)?;
+
+    println!("Starting Deepgram transcription from URL: {}", url);
+    println!("Mode: {}", if fast { "Fast" } else { "Real-time" });
 
-    // Always create a ready channel — stream_file waits on it before showing
-    // the progress bar, ensuring the request ID is printed first.
+    run_source_mode(
+        api_key,
+        options,
+        "audio stream",
+        false,
+        move |audio_tx, config_tx, stream_ready_rx| {
+            tokio::spawn(stream_url(
+                url,
+                retry,
+                audio_tx,
+                config_tx,
+                stream_ready_rx,
+                fast,
+            ))
+        },
+    )
+    .await
+}
+
+/// Stream audio from a source task that reports its format on `config_tx`
+/// and waits for every connection to be ready before sending audio.
+async fn run_source_mode<F>(
+    api_key: Option<String>,
+    options: StreamOptions,
+    source_name: &'static str,
+    raw_input: bool,
+    start_source: F,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnOnce(
+        mpsc::UnboundedSender<Vec<u8>>,
+        oneshot::Sender<(u32, u16)>,
+        oneshot::Receiver<()>,
+    ) -> JoinHandle<StreamResult>,
+{
+    let duration = options.duration;
+
+    let (audio_tx, audio_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let (config_tx, config_rx) = oneshot::channel::<(u32, u16)>();
+
+    // Always create a ready channel — the source waits on it before sending
+    // audio, ensuring the request ID is printed first.
     let (stream_ready_tx, stream_ready_rx) = oneshot::channel::<()>();
 
-    // Start streaming file audio in the background
-    let stream_task = tokio::spawn(async move {
-        file_reader
-            .stream_file(audio_tx, config_tx, Some(stream_ready_rx), fast)
-            .await
-    });
+    // Start streaming source audio in the background
+    let stream_task = start_source(audio_tx, config_tx, stream_ready_rx);
 
     // Wait for the audio configuration to be sent. If the channel closed without
-    // sending, stream_file failed early (e.g. unsupported format) — surface that error.
+    // sending, the source failed early (e.g. unsupported format) — surface that error.
     let (sample_rate, channels) = match config_rx.await {
         Ok(cfg) => cfg,
         Err(_) => {
             return match stream_task.await {
-                Ok(Err(e)) => Err(format!("Failed to read audio file: {e}").into()),
-                _ => Err("Failed to read audio file: unknown error".into()),
+                Ok(Err(e)) => Err(format!("Failed to read {source_name}: {e}").into()),
+                _ => Err(format!("Failed to read {source_name}: unknown error").into()),
             };
         }
     };
 
-    let client_config = DeepgramClientConfig {
-        api_key,
-        callback,
-        silent,
-        endpoint,
-        encoding,
-        sample_rate_override,
-        channels_override,
-        multichannel,
-        diarize,
-        detect_entities,
-        interim_results,
-        vad_events,
-        punctuate,
-        smart_format,
-        sentiment,
-        intents,
-        topics,
-        model,
-        version,
-        redact,
-        language,
-        endpointing,
-        utterance_end,
-        keyterm,
-        keywords,
-    };
+    let split_channels = options.split_channels;
+    let labels = options.channel_labels.clone();
+    let connections = options.connections;
+    let (target_sample_rate, mono) = (options.target_sample_rate, options.mono);
+    let dry_run = options.dry_run;
+    let record = options.record.clone().filter(|_| !dry_run);
+    let serve = options.serve.clone();
+    let mut client_config = options.client_config(api_key);
+    let (audio_rx, sample_rate, channels) = start_format_conversion(
+        audio_rx,
+        &mut client_config,
+        (sample_rate, channels),
+        target_sample_rate,
+        mono,
+    );
+    let (audio_rx, recording) = start_encoding_and_recording(
+        audio_rx,
+        &client_config,
+        (sample_rate, channels),
+        raw_input,
+        record.as_deref(),
+    )?;
+    let (audio_receivers, fanout_task, connection_channels) = start_audio_routing(
+        audio_rx,
+        &mut client_config,
+        connections,
+        split_channels,
+        labels.as_deref(),
+        channels,
+    )?;
+    let connections = audio_receivers.len();
+    if dry_run {
+        print_stream_dry_run(&client_config, sample_rate, connection_channels)?;
+        if connections > 1 {
+            println!("(each of the {connections} connections sends this request)");
+        }
+        return Ok(());
+    }
+    attach_recording(
+        &mut client_config,
+        recording.as_ref(),
+        sample_rate,
+        connection_channels,
+        split_channels,
+    )?;
+    attach_server(&mut client_config, serve.as_deref()).await?;
 
+    let mut shutdown_senders = Vec::with_capacity(connections);
     let mut ready_receivers = Vec::with_capacity(connections);
     let mut deepgram_tasks = Vec::with_capacity(connections);
     for (idx, audio_rx) in audio_receivers.into_iter().enumerate() {
@@ -378,10 +941,13 @@ async fn run_file_mode(
             idx + 1,
             connections,
             sample_rate,
-            channels,
-            audio_rx,
-            Some(ready_tx),
-            shutdown_rx,
+            connection_channels,
+            ClientInputs {
+                audio_rx,
+                ready_tx: Some(ready_tx),
+                shutdown_rx,
+                control_rx: None,
+            },
         )));
     }
 
@@ -393,11 +959,12 @@ async fn run_file_mode(
     });
 
     // Wait for either CTRL+C or all tasks to complete.
-    let mut tasks_future = Box::pin(wait_for_file_tasks(
+    let mut tasks_future = Box::pin(wait_for_source_tasks(
+        source_name,
         stream_task,
         fanout_task,
         deepgram_tasks,
-        connections,
+        connection_prefixes(&client_config, connections),
     ));
 
     tokio::select! {
@@ -409,10 +976,23 @@ async fn run_file_mode(
 
             tasks_future.await;
         }
+        _ = duration_elapsed(duration) => {
+            println!("\nReached --duration, closing connections...");
+            for shutdown_tx in shutdown_senders {
+                let _ = shutdown_tx.send(()).await;
+            }
+
+            tasks_future.await;
+        }
         _ = &mut tasks_future => {
             println!("\nTranscription completed successfully");
         }
     }
+    finish_recording(recording).await;
+
+    if let Some(latency) = &client_config.latency {
+        latency.report()?;
+    }
 
     Ok(())
 }
@@ -421,13 +1001,26 @@ async fn run_file_mode(
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
 
-    let cli = Cli::parse();
+    let args = profile::apply_profile(&Cli::command(), env::args_os().collect())?;
+    let cli = Cli::parse_from(args);
 
     match cli.command {
         Commands::Transcribe { args } => {
-            let api_key = api_key_for_endpoint(args.endpoint.as_deref())?;
+            let api_key = api_key_for_endpoint(args.options.endpoint.as_deref())?;
             transcribe::run_transcribe_mode(api_key, args).await?
         }
+        Commands::TranscribeBatch { args } => {
+            let api_key = api_key_for_endpoint(args.options.endpoint.as_deref())?;
+            batch::run_batch_mode(api_key, args).await?
+        }
+        Commands::Eval { args } => {
+            let api_key = api_key_for_endpoint(args.options.endpoint.as_deref())?;
+            eval::run_eval_mode(api_key, args).await?
+        }
+        Commands::Profile {
+            command: ProfileCommand::Show { options },
+        } => profile::run_profile_show(cli.profile.as_deref(), cli.profile_file, options),
+        Commands::Devices { host } => devices::run_list_devices(host)?,
         Commands::ListModels {
             include_outdated,
             endpoint,
@@ -436,125 +1029,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             models::run_list_models(api_key, endpoint, include_outdated).await?
         }
         Commands::Stream { source } => match source {
-            StreamSource::Microphone {
-                callback,
-                silent,
-                endpoint,
-                connections,
-                encoding,
-                sample_rate,
-                channels,
-                multichannel,
-                diarize,
-                detect_entities,
-                interim_results,
-                vad_events,
-                punctuate,
-                smart_format,
-                sentiment,
-                intents,
-                topics,
-                model,
-                version,
-                redact,
-                language,
-                endpointing,
-                utterance_end,
-                keyterm,
-                keywords,
-            } => {
-                let api_key = api_key_for_endpoint(endpoint.as_deref())?;
-                run_microphone_mode(
-                    api_key,
-                    connections,
-                    callback,
-                    silent,
-                    endpoint,
-                    encoding,
-                    sample_rate,
-                    channels,
-                    multichannel,
-                    diarize,
-                    detect_entities,
-                    interim_results,
-                    vad_events,
-                    punctuate,
-                    smart_format,
-                    sentiment,
-                    intents,
-                    topics,
-                    model,
-                    version,
-                    redact,
-                    language,
-                    endpointing,
-                    utterance_end,
-                    keyterm,
-                    keywords,
-                )
-                .await?
+            StreamSource::Microphone { input, options } => {
+                let api_key = api_key_for_endpoint(options.endpoint.as_deref())?;
+                run_microphone_mode(api_key, input, options).await?
             }
             StreamSource::File {
                 file,
                 fast,
-                callback,
-                silent,
-                endpoint,
-                connections,
-                encoding,
-                sample_rate,
-                channels,
-                multichannel,
-                diarize,
-                detect_entities,
-                interim_results,
-                vad_events,
-                punctuate,
-                smart_format,
-                sentiment,
-                intents,
-                topics,
-                model,
-                version,
-                redact,
-                language,
-                endpointing,
-                utterance_end,
-                keyterm,
-                keywords,
+                looping,
+                options,
+            } => {
+                let api_key = api_key_for_endpoint(options.endpoint.as_deref())?;
+                run_file_mode(api_key, file, fast, looping, options).await?
+            }
+            StreamSource::Stdin { raw, fast, options } => {
+                let api_key = api_key_for_endpoint(options.endpoint.as_deref())?;
+                run_stdin_mode(api_key, raw, fast, options).await?
+            }
+            StreamSource::Url {
+                url,
+                fast,
+                source_reconnect_attempts,
+                source_reconnect_backoff,
+                options,
             } => {
-                let api_key = api_key_for_endpoint(endpoint.as_deref())?;
-                run_file_mode(
-                    api_key,
-                    connections,
-                    file,
-                    fast,
-                    callback,
-                    silent,
-                    endpoint,
-                    encoding,
-                    sample_rate,
-                    channels,
-                    multichannel,
-                    diarize,
-                    detect_entities,
-                    interim_results,
-                    vad_events,
-                    punctuate,
-                    smart_format,
-                    sentiment,
-                    intents,
-                    topics,
-                    model,
-                    version,
-                    redact,
-                    language,
-                    endpointing,
-                    utterance_end,
-                    keyterm,
-                    keywords,
-                )
-                .await?
+                let api_key = api_key_for_endpoint(options.endpoint.as_deref())?;
+                let retry = SourceRetry {
+                    attempts: source_reconnect_attempts,
+                    backoff: Duration::from_millis(source_reconnect_backoff),
+                };
+                run_url_mode(api_key, url, retry, fast, options).await?
             }
         },
     }
@@ -0,0 +1,427 @@
+use clap::{ArgAction, Command, Subcommand};
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::deepgram::build_stream_url;
+use crate::stream::StreamOptions;
+
+#[derive(Subcommand)]
+pub(crate) enum ProfileCommand {
+    /// Print the streaming client configuration and query string that a profile,
+    /// merged with any flags given here, resolves to
+    Show {
+        #[command(flatten)]
+        options: StreamOptions,
+    },
+}
+
+/// Location of the profiles file when `--profile-file` is not given:
+/// `$XDG_CONFIG_HOME/deepgram/dg-stt.toml`, or `~/.config/deepgram/dg-stt.toml`.
+pub(crate) fn default_profile_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("deepgram").join("dg-stt.toml"))
+}
+
+/// Merge the profile selected by `--profile` into `args`.
+///
+/// Each key in the profile becomes the long flag of the same name (with `_`
+/// read as `-`), inserted right after the subcommand. Keys the command does
+/// not have are skipped, so one profile can serve streaming and pre-recorded
+/// commands alike. A key is also skipped when its flag, or a flag it conflicts
+/// with, is already on the command line, so CLI flags take precedence.
+pub(crate) fn apply_profile(
+    command: &Command,
+    args: Vec<OsString>,
+) -> Result<Vec<OsString>, Box<dyn std::error::Error>> {
+    let Some((name, path)) = profile_selection(&args)? else {
+        return Ok(args);
+    };
+    let values = load_profile(&path, &name)?;
+
+    let (subcommand, depth) = find_subcommand(command, &args);
+    let cli_args = &args[depth + 1..];
+    let mut flags = Vec::new();
+    for (key, value) in &values {
+        let long = key.replace('_', "-");
+        let Some(arg) = subcommand
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+        else {
+            if !any_command_has_flag(command, &long) {
+                return Err(format!(
+                    "Unknown option {key:?} in profile {name:?} ({})",
+                    path.display()
+                )
+                .into());
+            }
+            continue;
+        };
+
+        let on_cli = |arg: &clap::Arg| given_on_cli(arg, cli_args);
+        if on_cli(arg)
+            || subcommand
+                .get_arg_conflicts_with(arg)
+                .into_iter()
+                .any(on_cli)
+        {
+            continue;
+        }
+        flags.extend(profile_flag(&long, arg.get_action(), value).map_err(|e| {
+            format!(
+                "Invalid value for {key:?} in profile {name:?} ({}): {e}",
+                path.display()
+            )
+        })?);
+    }
+
+    let mut merged = args[..=depth].to_vec();
+    merged.extend(flags.into_iter().map(OsString::from));
+    merged.extend_from_slice(cli_args);
+    Ok(merged)
+}
+
+/// The `--profile` name and the file to read it from, if a profile was requested.
+fn profile_selection(
+    args: &[OsString],
+) -> Result<Option<(String, PathBuf)>, Box<dyn std::error::Error>> {
+    let name = flag_value(args, "--profile");
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    let path = match flag_value(args, "--profile-file") {
+        Some(path) => PathBuf::from(path),
+        None => default_profile_path()
+            .ok_or("Cannot locate the profiles file; pass --profile-file or set HOME")?,
+    };
+    Ok(Some((name, path)))
+}
+
+fn flag_value(args: &[OsString], flag: &str) -> Option<String> {
+    let mut args = args.iter().filter_map(|arg| arg.to_str());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == flag {
+            return args.next().map(str::to_string);
+        }
+        if let Some(value) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Read the `[profiles.<name>]` table from the profiles file.
+pub(crate) fn load_profile(
+    path: &Path,
+    name: &str,
+) -> Result<toml::Table, Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read profiles file {}: {e}", path.display()))?;
+    let mut file: toml::Table = text
+        .parse()
+        .map_err(|e| format!("Failed to parse profiles file {}: {e}", path.display()))?;
+
+    let Some(toml::Value::Table(mut profiles)) = file.remove("profiles") else {
+        return Err(format!("No [profiles.<name>] tables in {}", path.display()).into());
+    };
+    match profiles.remove(name) {
+        Some(toml::Value::Table(values)) => Ok(values),
+        Some(_) => Err(format!("profiles.{name} in {} must be a table", path.display()).into()),
+        None => {
+            let available: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            Err(format!(
+                "Profile {name:?} not found in {} (available: {})",
+                path.display(),
+                available.join(", ")
+            )
+            .into())
+        }
+    }
+}
+
+/// The innermost subcommand named in `args`, and the index of its name.
+/// Global flags such as `--profile` may come before a subcommand, so they and
+/// their values are stepped over on the way down.
+fn find_subcommand<'a>(command: &'a Command, args: &[OsString]) -> (&'a Command, usize) {
+    let mut current = command;
+    let mut depth = 0;
+    let mut globals: Vec<&clap::Arg> = Vec::new();
+    let mut tokens = args.iter().enumerate().skip(1);
+    while let Some((idx, arg)) = tokens.next() {
+        let Some(token) = arg.to_str() else {
+            break;
+        };
+        globals.extend(current.get_arguments().filter(|arg| arg.is_global_set()));
+        // Some(true) when the flag's value is the next token.
+        let global = globals.iter().find_map(|arg| {
+            let rest = token.strip_prefix("--")?.strip_prefix(arg.get_long()?)?;
+            match rest {
+                "" => Some(arg.get_action().takes_values()),
+                rest if rest.starts_with('=') => Some(false),
+                _ => None,
+            }
+        });
+        match global {
+            Some(true) => {
+                tokens.next();
+                continue;
+            }
+            Some(false) => continue,
+            None => {}
+        }
+        let Some(next) = current.find_subcommand(token) else {
+            break;
+        };
+        current = next;
+        depth = idx;
+    }
+    (current, depth)
+}
+
+fn any_command_has_flag(command: &Command, long: &str) -> bool {
+    command
+        .get_arguments()
+        .any(|arg| arg.get_long() == Some(long))
+        || command
+            .get_subcommands()
+            .any(|subcommand| any_command_has_flag(subcommand, long))
+}
+
+fn given_on_cli(arg: &clap::Arg, cli_args: &[OsString]) -> bool {
+    let long = arg.get_long().map(|long| format!("--{long}"));
+    let short = arg.get_short().map(|short| format!("-{short}"));
+    cli_args
+        .iter()
+        .filter_map(|token| token.to_str())
+        .take_while(|token| *token != "--")
+        .any(|token| {
+            long.as_deref().is_some_and(|long| {
+                token == long
+                    || token
+                        .strip_prefix(long)
+                        .is_some_and(|rest| rest.starts_with('='))
+            }) || short
+                .as_deref()
+                .is_some_and(|short| token.starts_with(short) && !token.starts_with("--"))
+        })
+}
+
+/// Command-line tokens for one profile value. Switches take `true` or `false`;
+/// arrays repeat a repeatable flag, or are joined with commas otherwise.
+fn profile_flag(
+    long: &str,
+    action: &ArgAction,
+    value: &toml::Value,
+) -> Result<Vec<String>, String> {
+    let flag = format!("--{long}");
+    if matches!(action, ArgAction::SetTrue) {
+        return match value {
+            toml::Value::Boolean(true) => Ok(vec![flag]),
+            toml::Value::Boolean(false) => Ok(Vec::new()),
+            _ => Err("expected true or false".to_string()),
+        };
+    }
+
+    match value {
+        toml::Value::Array(items) => {
+            let items = items.iter().map(scalar).collect::<Result<Vec<_>, _>>()?;
+            if matches!(action, ArgAction::Append) {
+                Ok(items
+                    .into_iter()
+                    .flat_map(|item| [flag.clone(), item])
+                    .collect())
+            } else {
+                Ok(vec![flag, items.join(",")])
+            }
+        }
+        value => Ok(vec![flag, scalar(value)?]),
+    }
+}
+
+fn scalar(value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(text) => Ok(text.clone()),
+        toml::Value::Integer(number) => Ok(number.to_string()),
+        toml::Value::Float(number) => Ok(number.to_string()),
+        toml::Value::Boolean(flag) => Ok(flag.to_string()),
+        _ => Err("expected a string, number, boolean, or array of those".to_string()),
+    }
+}
+
+/// Print the resolved streaming configuration for `profile show`.
+pub(crate) fn run_profile_show(
+    profile: Option<&str>,
+    profile_file: Option<PathBuf>,
+    options: StreamOptions,
+) {
+    match profile {
+        Some(name) => {
+            let path = profile_file
+                .or_else(default_profile_path)
+                .unwrap_or_default();
+            println!("Profile: {name} ({})", path.display());
+        }
+        None => println!("Profile: none (pass --profile <NAME>)"),
+    }
+
+    let api_key = env::var("DEEPGRAM_API_KEY")
+        .ok()
+        .map(|_| "<redacted>".to_string());
+    let config = options.client_config(api_key);
+    println!("\n{config:#?}");
+    println!("\nQuery: {}", build_stream_url(&config, 16000, 1));
+    if config.sample_rate_override.is_none() || config.channels_override.is_none() {
+        println!(
+            "Without --sample-rate and --channels, sample_rate and channels follow the audio source; 16000 Hz mono is shown."
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_profile;
+    use crate::cli::{Cli, Commands};
+    use crate::stream::StreamSource;
+    use clap::{CommandFactory, Parser};
+    use std::ffi::OsString;
+
+    fn merged(profile_file: &std::path::Path, args: &[&str]) -> Result<Vec<String>, String> {
+        let mut args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        args.push("--profile-file".into());
+        args.push(profile_file.into());
+        apply_profile(&Cli::command(), args)
+            .map(|args| {
+                args.into_iter()
+                    .map(|arg| arg.into_string().unwrap())
+                    .collect()
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn merges_profile_under_cli_flags() {
+        let path = std::env::temp_dir().join(format!("dg-stt-profile-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+[profiles.support]
+model = "nova-3"
+keyterm = ["Acme", "WidgetPro"]
+redact = "pci"
+diarize = true
+smart_format = false
+output = "json"
+endpointing = 300
+
+[profiles.typo]
+diarise = true
+"#,
+        )
+        .unwrap();
+
+        let args = merged(
+            &path,
+            &[
+                "dg-stt",
+                "stream",
+                "file",
+                "--file",
+                "a.wav",
+                "--profile",
+                "support",
+                "--model",
+                "nova-2",
+                "--keywords",
+                "Acme:2",
+            ],
+        )
+        .unwrap();
+        // --model and --keywords (which conflicts with keyterm) come from the CLI;
+        // output is a transcribe flag, so streaming ignores it.
+        assert_eq!(
+            args[..9],
+            [
+                "dg-stt",
+                "stream",
+                "file",
+                "--diarize",
+                "--endpointing",
+                "300",
+                "--redact",
+                "pci",
+                "--file"
+            ]
+        );
+        let Commands::Stream {
+            source: StreamSource::File { options, .. },
+        } = Cli::try_parse_from(&args).unwrap().command
+        else {
+            panic!("expected stream file");
+        };
+        assert_eq!(options.model.as_deref(), Some("nova-2"));
+        assert!(options.diarize && !options.smart_format);
+
+        let args = merged(
+            &path,
+            &["dg-stt", "transcribe", "--profile=support", "-f", "a.wav"],
+        )
+        .unwrap();
+        assert!(
+            args.windows(2)
+                .any(|w| w == ["--keyterm", "Acme,WidgetPro"])
+        );
+        assert!(args.windows(2).any(|w| w == ["--output", "json"]));
+
+        let typo = merged(&path, &["dg-stt", "transcribe", "--profile", "typo"]).unwrap_err();
+        assert!(typo.contains("Unknown option \"diarise\""), "{typo}");
+        let missing = merged(&path, &["dg-stt", "transcribe", "--profile", "nope"]).unwrap_err();
+        assert!(missing.contains("available: support, typo"), "{missing}");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn applies_a_profile_given_before_the_subcommand() {
+        let path =
+            std::env::temp_dir().join(format!("dg-stt-profile-global-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[profiles.support]\ndiarize = true\nmodel = \"nova-3\"\n",
+        )
+        .unwrap();
+
+        for args in [
+            ["dg-stt", "--profile", "support", "stream", "microphone"],
+            [
+                "dg-stt",
+                "stream",
+                "--profile=support",
+                "microphone",
+                "--punctuate",
+            ],
+        ] {
+            let args = merged(&path, &args).unwrap();
+            let at = args.iter().position(|arg| arg == "microphone").unwrap();
+            assert_eq!(args[at + 1..at + 4], ["--diarize", "--model", "nova-3"]);
+            let Commands::Stream {
+                source: StreamSource::Microphone { options, .. },
+            } = Cli::try_parse_from(&args).unwrap().command
+            else {
+                panic!("expected stream microphone");
+            };
+            assert!(options.diarize);
+            assert_eq!(options.model.as_deref(), Some("nova-3"));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,12 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::audio::connection_prefix;
+use crate::latency::LatencyCollector;
+use crate::record::SessionRecord;
+use crate::serve::TranscriptServer;
+use crate::stream::ReconnectArgs;
+use crate::subtitles::SubtitleArgs;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct DeepgramResponse {
@@ -9,6 +17,26 @@ pub(crate) struct DeepgramResponse {
     // or an array. Keep this raw until the message type is known.
     #[serde(default)]
     pub(crate) channel: Option<serde_json::Value>,
+    #[serde(default)]
+    pub(crate) is_final: bool,
+    #[serde(default)]
+    pub(crate) speech_final: bool,
+    /// True when the result was produced in response to a `Finalize` message.
+    #[serde(default)]
+    pub(crate) from_finalize: bool,
+    /// `[channel, total channels]` for multichannel Results.
+    #[serde(default)]
+    pub(crate) channel_index: Vec<u32>,
+    #[serde(default)]
+    pub(crate) start: f64,
+    #[serde(default)]
+    pub(crate) duration: f64,
+    /// Start of detected speech, for SpeechStarted events.
+    #[serde(default)]
+    pub(crate) timestamp: Option<f64>,
+    /// End of the last finalized word, for UtteranceEnd events.
+    #[serde(default)]
+    pub(crate) last_word_end: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -24,16 +52,30 @@ pub(crate) struct Alternative {
     pub(crate) words: Vec<Word>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct Word {
     pub(crate) word: String,
+    #[serde(default)]
+    pub(crate) start: f64,
+    #[serde(default)]
+    pub(crate) end: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) confidence: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) speaker: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) speaker_confidence: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) punctuated_word: Option<String>,
+    /// Language of the word, with `language=multi`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) language: Option<String>,
 }
 
 pub(crate) type StreamError = Box<dyn std::error::Error + Send + Sync>;
 pub(crate) type StreamResult = Result<(), StreamError>;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub(crate) struct DeepgramClientConfig {
     pub(crate) api_key: Option<String>,
     pub(crate) callback: Option<String>,
@@ -60,4 +102,40 @@ pub(crate) struct DeepgramClientConfig {
     pub(crate) utterance_end: Option<u32>,
     pub(crate) keyterm: Option<String>,
     pub(crate) keywords: Option<String>,
+    /// `--param` query parameters, appended after the ones built from flags.
+    pub(crate) extra_params: Vec<(String, String)>,
+    pub(crate) subtitles: Option<PathBuf>,
+    pub(crate) subtitle_options: SubtitleArgs,
+    pub(crate) reconnect: ReconnectArgs,
+    pub(crate) transcript_log: Option<PathBuf>,
+    pub(crate) ramp_up: Option<f64>,
+    pub(crate) latency: Option<LatencyCollector>,
+    /// The `--record` sidecar, which each connection adds its request IDs to.
+    pub(crate) recording: Option<SessionRecord>,
+    /// The `--serve` server that results and events are republished to.
+    pub(crate) server: Option<TranscriptServer>,
+    /// With --split-channels, the label of the channel each connection carries,
+    /// indexed by connection.
+    pub(crate) channel_labels: Vec<String>,
+}
+
+impl DeepgramClientConfig {
+    pub(crate) fn channel_label(&self, connection_id: usize) -> Option<&str> {
+        self.channel_labels
+            .get(connection_id - 1)
+            .map(String::as_str)
+    }
+
+    /// Console prefix for a connection: its channel label with --split-channels,
+    /// otherwise its number when there are several connections.
+    pub(crate) fn connection_prefix(
+        &self,
+        connection_id: usize,
+        connection_count: usize,
+    ) -> String {
+        match self.channel_label(connection_id) {
+            Some(label) => format!("[{label}] "),
+            None => connection_prefix(connection_id, connection_count),
+        }
+    }
 }
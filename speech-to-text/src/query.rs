@@ -0,0 +1,76 @@
+/// Parse a `--param KEY=VALUE` argument. The value may itself contain `=`.
+pub(crate) fn parse_query_param(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got {arg:?}")),
+    }
+}
+
+/// Append `--param` values to the query parameters, URL-encoding each key and
+/// value. They are added after the parameters built from dedicated flags.
+pub(crate) fn push_extra_params(params: &mut Vec<String>, extra: &[(String, String)]) {
+    for (key, value) in extra {
+        params.push(format!(
+            "{}={}",
+            urlencoding::encode(key),
+            urlencoding::encode(value)
+        ));
+    }
+}
+
+/// `Token ****abcd`, enough to tell which API key a request would use.
+pub(crate) fn redacted_authorization(api_key: &str) -> String {
+    let chars: Vec<char> = api_key.chars().collect();
+    let tail: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    format!("Token ****{tail}")
+}
+
+/// Print the request `--dry-run` would otherwise send.
+pub(crate) fn print_dry_run(method: &str, url: &str, headers: &[(&str, String)]) {
+    println!("Dry run: no request sent");
+    println!("{method} {url}");
+    for (name, value) in headers {
+        println!("{name}: {value}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_query_param, push_extra_params, redacted_authorization};
+
+    #[test]
+    fn parses_and_encodes_extra_params() {
+        assert_eq!(
+            parse_query_param("extra=team:support=1"),
+            Ok(("extra".to_string(), "team:support=1".to_string()))
+        );
+        assert_eq!(
+            parse_query_param("filler_words="),
+            Ok(("filler_words".to_string(), String::new()))
+        );
+        assert!(parse_query_param("numerals").is_err());
+        assert!(parse_query_param("=true").is_err());
+
+        let mut params = vec!["model=nova-3".to_string()];
+        push_extra_params(
+            &mut params,
+            &[
+                ("tag".to_string(), "load test".to_string()),
+                ("replace".to_string(), "acme:Acme&Co".to_string()),
+            ],
+        );
+        assert_eq!(
+            params,
+            [
+                "model=nova-3",
+                "tag=load%20test",
+                "replace=acme%3AAcme%26Co"
+            ]
+        );
+
+        assert_eq!(redacted_authorization("dg_secret_1234"), "Token ****1234");
+        assert_eq!(redacted_authorization("ab"), "Token ****ab");
+    }
+}
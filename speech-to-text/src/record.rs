@@ -0,0 +1,399 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// How often the WAV header and sidecar are brought up to date while recording,
+/// so a session that is killed outright still leaves a playable file.
+const PATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_ALAW: u16 = 6;
+const WAVE_FORMAT_MULAW: u16 = 7;
+
+/// Layout of the audio written by `--record`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct WavFormat {
+    pub(crate) sample_rate: u32,
+    pub(crate) channels: u16,
+    format_tag: u16,
+    bits_per_sample: u16,
+}
+
+impl WavFormat {
+    /// The WAV layout for audio in `encoding`, or `None` if a WAV file cannot
+    /// hold it as-is (compressed encodings such as Opus or FLAC).
+    pub(crate) fn for_encoding(encoding: &str, sample_rate: u32, channels: u16) -> Option<Self> {
+        let (format_tag, bits_per_sample) = match encoding.to_ascii_lowercase().as_str() {
+            "linear16" => (WAVE_FORMAT_PCM, 16),
+            "linear32" => (WAVE_FORMAT_PCM, 32),
+            "mulaw" => (WAVE_FORMAT_MULAW, 8),
+            "alaw" => (WAVE_FORMAT_ALAW, 8),
+            _ => return None,
+        };
+        Some(WavFormat {
+            sample_rate,
+            channels,
+            format_tag,
+            bits_per_sample,
+        })
+    }
+
+    pub(crate) fn encoding(&self) -> &'static str {
+        match (self.format_tag, self.bits_per_sample) {
+            (WAVE_FORMAT_MULAW, _) => "mulaw",
+            (WAVE_FORMAT_ALAW, _) => "alaw",
+            (_, 32) => "linear32",
+            _ => "linear16",
+        }
+    }
+
+    fn bytes_per_second(&self) -> u32 {
+        self.sample_rate * self.block_align() as u32
+    }
+
+    fn block_align(&self) -> u16 {
+        self.channels * self.bits_per_sample / 8
+    }
+
+    /// RIFF header for `data_bytes` of audio. Non-PCM formats carry the
+    /// 18-byte `fmt ` chunk with an empty extension.
+    fn header(&self, data_bytes: u32) -> Vec<u8> {
+        let fmt_size: u32 = if self.format_tag == WAVE_FORMAT_PCM {
+            16
+        } else {
+            18
+        };
+        let mut header = Vec::with_capacity(46);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(4 + 8 + fmt_size + 8 + data_bytes).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&fmt_size.to_le_bytes());
+        header.extend_from_slice(&self.format_tag.to_le_bytes());
+        header.extend_from_slice(&self.channels.to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&self.bytes_per_second().to_le_bytes());
+        header.extend_from_slice(&self.block_align().to_le_bytes());
+        header.extend_from_slice(&self.bits_per_sample.to_le_bytes());
+        if fmt_size == 18 {
+            header.extend_from_slice(&0u16.to_le_bytes());
+        }
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_bytes.to_le_bytes());
+        header
+    }
+}
+
+/// Writes audio to a WAV file, rewriting the header sizes as it grows.
+struct WavWriter {
+    file: BufWriter<File>,
+    format: WavFormat,
+    data_bytes: u32,
+}
+
+impl WavWriter {
+    fn create(path: &Path, format: WavFormat) -> std::io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(&format.header(0))?;
+        Ok(WavWriter {
+            file,
+            format,
+            data_bytes: 0,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        // RIFF sizes are 32-bit; stop recording rather than corrupt the header.
+        let room = (u32::MAX - 64 - self.data_bytes) as usize;
+        let data = &data[..data.len().min(room)];
+        self.file.write_all(data)?;
+        self.data_bytes += data.len() as u32;
+        Ok(())
+    }
+
+    /// Flush the audio and patch the header to cover it.
+    fn patch_header(&mut self) -> std::io::Result<()> {
+        let header = self.format.header(self.data_bytes);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.flush()
+    }
+
+    fn duration(&self) -> f64 {
+        self.data_bytes as f64 / self.format.bytes_per_second() as f64
+    }
+}
+
+/// The sidecar JSON describing a recording: its format, the URL it was sent
+/// to, and the request ID of every connection that carried it.
+#[derive(Debug, Serialize)]
+struct SessionInfo {
+    audio: PathBuf,
+    sample_rate: u32,
+    channels: u16,
+    /// Encoding of the audio in the WAV file.
+    encoding: &'static str,
+    /// Encoding the audio was sent in, when it differs from the WAV file's
+    /// (Opus is recorded before encoding).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sent_encoding: Option<String>,
+    split_channels: bool,
+    url: String,
+    duration: f64,
+    complete: bool,
+    connections: Vec<ConnectionRecord>,
+    replay: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ConnectionRecord {
+    connection: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel_label: Option<String>,
+    request_ids: Vec<String>,
+}
+
+/// Shared handle to the `--record` sidecar. Connections add their request IDs
+/// as they connect (and reconnect); each change rewrites the file.
+#[derive(Clone, Debug)]
+pub(crate) struct SessionRecord {
+    path: PathBuf,
+    info: Arc<Mutex<SessionInfo>>,
+}
+
+impl SessionRecord {
+    /// `call.wav` -> `call.json`.
+    pub(crate) fn sidecar_path(audio: &Path) -> PathBuf {
+        audio.with_extension("json")
+    }
+
+    fn new(audio: &Path, format: WavFormat, sent_encoding: Option<String>) -> Self {
+        SessionRecord {
+            path: Self::sidecar_path(audio),
+            info: Arc::new(Mutex::new(SessionInfo {
+                audio: audio.to_path_buf(),
+                sample_rate: format.sample_rate,
+                channels: format.channels,
+                encoding: format.encoding(),
+                sent_encoding,
+                split_channels: false,
+                url: String::new(),
+                duration: 0.0,
+                complete: false,
+                connections: Vec::new(),
+                replay: String::new(),
+            })),
+        }
+    }
+
+    /// Record the streaming URL (with its query) the connections open and how
+    /// to replay the session with `stream file`.
+    pub(crate) fn set_url(&self, url: String, split_channels: bool) -> std::io::Result<()> {
+        {
+            let mut info = self.info.lock().unwrap();
+            let mut replay = format!(
+                "dg-stt stream file --file {}",
+                shell_quote(&info.audio.display().to_string())
+            );
+            // The query's encoding is the one sent, so replay re-encodes to match.
+            let sent = info.sent_encoding.as_deref().unwrap_or(info.encoding);
+            if sent != "linear16" {
+                replay.push_str(&format!(" --encoding {sent}"));
+            }
+            if split_channels {
+                replay.push_str(" --split-channels");
+            }
+            info.url = url;
+            info.split_channels = split_channels;
+            info.replay = replay;
+        }
+        self.save()
+    }
+
+    pub(crate) fn add_request_id(
+        &self,
+        connection: usize,
+        channel_label: Option<&str>,
+        request_id: &str,
+    ) -> std::io::Result<()> {
+        {
+            let mut info = self.info.lock().unwrap();
+            if !info
+                .connections
+                .iter()
+                .any(|record| record.connection == connection)
+            {
+                info.connections.push(ConnectionRecord {
+                    connection,
+                    channel_label: channel_label.map(str::to_string),
+                    request_ids: Vec::new(),
+                });
+                info.connections.sort_by_key(|record| record.connection);
+            }
+            if let Some(record) = info
+                .connections
+                .iter_mut()
+                .find(|record| record.connection == connection)
+            {
+                record.request_ids.push(request_id.to_string());
+            }
+        }
+        self.save()
+    }
+
+    fn set_progress(&self, duration: f64, complete: bool) -> std::io::Result<()> {
+        {
+            let mut info = self.info.lock().unwrap();
+            info.duration = duration;
+            info.complete = complete;
+        }
+        self.save()
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(&*self.info.lock().unwrap())?;
+        std::fs::write(&self.path, json)
+    }
+}
+
+fn shell_quote(text: &str) -> String {
+    if text
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c))
+    {
+        text.to_string()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+/// Create the `--record` WAV file and its sidecar, and tee `source_rx` into
+/// the file in a background task. The returned task finalizes the WAV header
+/// and marks the sidecar complete once the source ends or nothing is left to
+/// forward to.
+pub(crate) fn start_recording(
+    mut source_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    path: &Path,
+    format: WavFormat,
+    sent_encoding: Option<String>,
+) -> std::io::Result<(
+    mpsc::UnboundedReceiver<Vec<u8>>,
+    JoinHandle<()>,
+    SessionRecord,
+)> {
+    let mut writer = WavWriter::create(path, format)?;
+    let session = SessionRecord::new(path, format, sent_encoding);
+    session.save()?;
+    println!(
+        "Recording sent audio to {} ({} Hz, {} channel(s), {}); session details in {}",
+        path.display(),
+        format.sample_rate,
+        format.channels,
+        format.encoding(),
+        session.path.display()
+    );
+
+    let (tx, rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let task_session = session.clone();
+    let path = path.to_path_buf();
+    let task = tokio::spawn(async move {
+        let mut last_patch = Instant::now();
+        let mut failed = false;
+        while let Some(chunk) = source_rx.recv().await {
+            if !failed {
+                let written = writer.write(&chunk).and_then(|()| {
+                    if last_patch.elapsed() < PATCH_INTERVAL {
+                        return Ok(());
+                    }
+                    last_patch = Instant::now();
+                    writer.patch_header()?;
+                    task_session.set_progress(writer.duration(), false)
+                });
+                if let Err(e) = written {
+                    eprintln!("Failed to write recording {}: {}", path.display(), e);
+                    failed = true;
+                }
+            }
+            if tx.send(chunk).is_err() {
+                break;
+            }
+        }
+
+        let finished = writer
+            .patch_header()
+            .and_then(|()| task_session.set_progress(writer.duration(), true));
+        match finished {
+            Ok(()) => println!(
+                "Saved {:.1}s of sent audio to {}",
+                writer.duration(),
+                path.display()
+            ),
+            Err(e) => eprintln!("Failed to finalize recording {}: {}", path.display(), e),
+        }
+    });
+    Ok((rx, task, session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionRecord, WavFormat, start_recording};
+    use crate::audio::wav_data_len;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn records_sent_audio_with_a_finalized_header_and_sidecar() {
+        let path = std::env::temp_dir().join(format!("dg-stt-record-{}.wav", std::process::id()));
+        let format = WavFormat::for_encoding("mulaw", 8000, 2).unwrap();
+        let (source_tx, source_rx) = mpsc::unbounded_channel();
+        let (mut sent_rx, task, session) = start_recording(source_rx, &path, format, None).unwrap();
+        session
+            .set_url(
+                "wss://api.deepgram.com/v1/listen?encoding=mulaw&sample_rate=8000".to_string(),
+                false,
+            )
+            .unwrap();
+        session.add_request_id(1, None, "req-a").unwrap();
+        session.add_request_id(1, None, "req-b").unwrap();
+
+        source_tx.send(vec![0x7f; 1000]).unwrap();
+        source_tx.send(vec![0xff; 600]).unwrap();
+        drop(source_tx);
+        task.await.unwrap();
+        assert_eq!(sent_rx.recv().await.unwrap().len(), 1000);
+        assert_eq!(sent_rx.recv().await.unwrap().len(), 600);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(wav_data_len(&bytes), Some(1600));
+        assert_eq!(&bytes[20..22], &7u16.to_le_bytes());
+        assert_eq!(
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize,
+            bytes.len() - 8
+        );
+
+        let sidecar_path = SessionRecord::sidecar_path(&path);
+        let sidecar: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&sidecar_path).unwrap()).unwrap();
+        assert_eq!(sidecar["encoding"], "mulaw");
+        assert_eq!(sidecar["duration"], 0.1);
+        assert_eq!(sidecar["complete"], true);
+        assert_eq!(
+            sidecar["connections"][0]["request_ids"],
+            serde_json::json!(["req-a", "req-b"])
+        );
+        assert!(
+            sidecar["replay"]
+                .as_str()
+                .unwrap()
+                .ends_with(".wav --encoding mulaw")
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&sidecar_path).unwrap();
+        assert!(WavFormat::for_encoding("opus", 48000, 1).is_none());
+    }
+}
@@ -0,0 +1,312 @@
+use std::f64::consts::PI;
+use tokio::sync::mpsc;
+
+/// Kernel half-width in input samples when not downsampling. Downsampling
+/// widens the kernel by the rate ratio so the transition band stays as sharp.
+const BASE_HALF_TAPS: usize = 16;
+
+/// Fraction of the lower Nyquist frequency kept by the low-pass filter.
+const ROLLOFF: f64 = 0.94;
+
+/// Converts interleaved linear16 audio to a different sample rate and/or to
+/// mono, carrying partial samples and filter history across chunks.
+pub(crate) struct AudioConverter {
+    channels: usize,
+    mono: bool,
+    pending: Vec<u8>,
+    resampler: Option<Resampler>,
+}
+
+impl AudioConverter {
+    pub(crate) fn new(
+        sample_rate: u32,
+        channels: u16,
+        target_sample_rate: Option<u32>,
+        mono: bool,
+    ) -> Self {
+        let mono = mono && channels > 1;
+        let output_channels = if mono { 1 } else { channels as usize };
+        let resampler = target_sample_rate
+            .filter(|&target| target != sample_rate)
+            .map(|target| Resampler::new(sample_rate, target, output_channels));
+        AudioConverter {
+            channels: channels as usize,
+            mono,
+            pending: Vec::new(),
+            resampler,
+        }
+    }
+
+    /// Whether the converter changes the audio at all.
+    pub(crate) fn is_identity(&self) -> bool {
+        !self.mono && self.resampler.is_none()
+    }
+
+    pub(crate) fn convert(&mut self, data: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(data);
+        let frame_bytes = self.channels * 2;
+        let whole = self.pending.len() - self.pending.len() % frame_bytes;
+        let samples: Vec<f32> = self.pending[..whole]
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32)
+            .collect();
+        self.pending.drain(..whole);
+
+        let samples = if self.mono {
+            samples
+                .chunks_exact(self.channels)
+                .map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+                .collect()
+        } else {
+            samples
+        };
+        let samples = match &mut self.resampler {
+            Some(resampler) => resampler.process(&samples),
+            None => samples,
+        };
+        to_linear16(&samples)
+    }
+
+    /// Flush the audio still held back by the resampling filter.
+    pub(crate) fn finish(&mut self) -> Vec<u8> {
+        match &mut self.resampler {
+            Some(resampler) => to_linear16(&resampler.finish()),
+            None => Vec::new(),
+        }
+    }
+}
+
+fn to_linear16(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|&sample| {
+            (sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16).to_le_bytes()
+        })
+        .collect()
+}
+
+/// Streaming polyphase resampler with a Blackman-windowed sinc low-pass.
+///
+/// Output sample `n` sits at input position `n * from / to`. Reducing the
+/// ratio to `step / phases` makes that position exact in integers, and the
+/// filter taps for each of the `phases` fractional offsets are computed once.
+struct Resampler {
+    channels: usize,
+    step: u64,
+    phases: u64,
+    half: usize,
+    /// `phases` rows of `2 * half` taps, each normalized to unity gain.
+    table: Vec<Vec<f32>>,
+    /// Interleaved input frames, starting `half` frames before frame `consumed`.
+    history: Vec<f32>,
+    consumed: u64,
+    input_frames: u64,
+    output_frames: u64,
+}
+
+impl Resampler {
+    fn new(from: u32, to: u32, channels: usize) -> Self {
+        let divisor = gcd(from as u64, to as u64);
+        let step = from as u64 / divisor;
+        let phases = to as u64 / divisor;
+        let cutoff = (to as f64 / from as f64).min(1.0) * ROLLOFF;
+        let half = (BASE_HALF_TAPS as f64 / cutoff).ceil() as usize;
+
+        let table = (0..phases)
+            .map(|phase| {
+                let frac = phase as f64 / phases as f64;
+                let taps: Vec<f64> = (0..2 * half)
+                    .map(|m| {
+                        let x = frac + (half - 1) as f64 - m as f64;
+                        cutoff * sinc(cutoff * x) * blackman(x / half as f64)
+                    })
+                    .collect();
+                let gain: f64 = taps.iter().sum();
+                taps.iter().map(|tap| (tap / gain) as f32).collect()
+            })
+            .collect();
+
+        Resampler {
+            channels,
+            step,
+            phases,
+            half,
+            table,
+            history: vec![0.0; half * channels],
+            consumed: 0,
+            input_frames: 0,
+            output_frames: 0,
+        }
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.input_frames += (samples.len() / self.channels) as u64;
+        self.history.extend_from_slice(samples);
+        self.drain_outputs(u64::MAX)
+    }
+
+    fn finish(&mut self) -> Vec<f32> {
+        // Pad with silence so the last outputs see a full kernel, but stop at
+        // the output that corresponds to the end of the input.
+        let end = self.input_frames * self.phases;
+        self.history
+            .extend(std::iter::repeat_n(0.0, self.half * self.channels));
+        self.drain_outputs(end)
+    }
+
+    /// Produce outputs while the kernel for the next one is fully buffered and
+    /// its position (in units of `1 / phases` input frames) is before `end`.
+    fn drain_outputs(&mut self, end: u64) -> Vec<f32> {
+        let mut output = Vec::new();
+        let buffered = (self.history.len() / self.channels) as u64;
+        loop {
+            let position = self.output_frames * self.step;
+            let frame = position / self.phases;
+            // Taps cover frames frame+1-half ..= frame+half, which sit at
+            // history indices frame+1-consumed .. frame+2*half-consumed.
+            let first = frame + 1 - self.consumed;
+            if position >= end || first + 2 * self.half as u64 > buffered {
+                break;
+            }
+            let taps = &self.table[(position % self.phases) as usize];
+            for channel in 0..self.channels {
+                let sample = taps
+                    .iter()
+                    .enumerate()
+                    .map(|(m, tap)| {
+                        tap * self.history[(first as usize + m) * self.channels + channel]
+                    })
+                    .sum();
+                output.push(sample);
+            }
+            self.output_frames += 1;
+        }
+
+        let next_first = (self.output_frames * self.step / self.phases + 1 - self.consumed)
+            .min(buffered) as usize;
+        self.history.drain(..next_first * self.channels);
+        self.consumed += next_first as u64;
+        output
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman window over `[-1, 1]`.
+fn blackman(u: f64) -> f64 {
+    if u.abs() > 1.0 {
+        0.0
+    } else {
+        0.42 + 0.5 * (PI * u).cos() + 0.08 * (2.0 * PI * u).cos()
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Convert audio from `source_rx` in a background task, returning the
+/// converted stream. The task ends when the source does.
+pub(crate) fn start_audio_conversion(
+    mut source_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    mut converter: AudioConverter,
+) -> mpsc::UnboundedReceiver<Vec<u8>> {
+    let (tx, rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        while let Some(chunk) = source_rx.recv().await {
+            let converted = converter.convert(&chunk);
+            if !converted.is_empty() && tx.send(converted).is_err() {
+                return;
+            }
+        }
+        let tail = converter.finish();
+        if !tail.is_empty() {
+            let _ = tx.send(tail);
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AudioConverter;
+    use std::f64::consts::PI;
+
+    fn tone(frequency: f64, sample_rate: u32, seconds: f64, amplitude: f64) -> Vec<i16> {
+        (0..(sample_rate as f64 * seconds) as usize)
+            .map(|n| {
+                (amplitude * (2.0 * PI * frequency * n as f64 / sample_rate as f64).sin()) as i16
+            })
+            .collect()
+    }
+
+    fn bytes(samples: &[i16]) -> Vec<u8> {
+        samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect()
+    }
+
+    fn samples(bytes: &[u8]) -> Vec<i16> {
+        bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    fn rms(samples: &[i16]) -> f64 {
+        (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    /// Convert in uneven chunks, including one that splits a sample.
+    fn convert(converter: &mut AudioConverter, input: &[u8]) -> Vec<i16> {
+        let mut output = Vec::new();
+        for chunk in input.chunks(1001) {
+            output.extend(converter.convert(chunk));
+        }
+        output.extend(converter.finish());
+        samples(&output)
+    }
+
+    #[test]
+    fn downsampling_keeps_passband_and_filters_above_nyquist() {
+        let mut converter = AudioConverter::new(48000, 1, Some(8000), false);
+        let passed = convert(&mut converter, &bytes(&tone(1000.0, 48000, 1.0, 10000.0)));
+        assert_eq!(passed.len(), 8000);
+        // Compare away from the edges, where the filter sees partial input.
+        let expected = rms(&tone(1000.0, 8000, 1.0, 10000.0)[400..7600]);
+        assert!((rms(&passed[400..7600]) / expected - 1.0).abs() < 0.02);
+
+        // 6 kHz would alias to 2 kHz at 8 kHz without the low-pass.
+        let mut converter = AudioConverter::new(48000, 1, Some(8000), false);
+        let aliased = convert(&mut converter, &bytes(&tone(6000.0, 48000, 1.0, 10000.0)));
+        assert!(rms(&aliased[400..7600]) < 10.0);
+    }
+
+    #[test]
+    fn upsamples_fractional_ratios_and_downmixes() {
+        let left = tone(440.0, 44100, 0.5, 8000.0);
+        let stereo: Vec<i16> = left.iter().flat_map(|&s| [s, s / 2]).collect();
+        let mut converter = AudioConverter::new(44100, 2, Some(48000), true);
+        let output = convert(&mut converter, &bytes(&stereo));
+        assert_eq!(output.len(), 24000);
+        let expected = rms(&tone(440.0, 48000, 0.5, 6000.0)[1000..23000]);
+        assert!((rms(&output[1000..23000]) / expected - 1.0).abs() < 0.02);
+
+        let mut converter = AudioConverter::new(16000, 2, None, true);
+        assert_eq!(
+            samples(&converter.convert(&bytes(&[100, 300, -50, 50]))),
+            vec![200, 0]
+        );
+        assert!(AudioConverter::new(16000, 1, Some(16000), true).is_identity());
+    }
+}
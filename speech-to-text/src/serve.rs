@@ -0,0 +1,302 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::{Message, Role};
+
+use crate::protocol::Word;
+
+/// Events a slow subscriber may fall behind by before it skips ahead.
+const EVENT_BUFFER: usize = 256;
+
+/// How often an idle Server-Sent Events stream gets a comment line, so
+/// proxies and browsers keep it open.
+const SSE_KEEPALIVE: Duration = Duration::from_secs(15);
+
+const CAPTIONS_PAGE: &str = include_str!("captions.html");
+
+/// A transcript event as republished by `--serve`, with timestamps on the
+/// stream's continuous timeline.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ServeEvent<'a> {
+    Transcript {
+        connection: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        channel_label: Option<&'a str>,
+        request_id: Option<&'a str>,
+        is_final: bool,
+        speech_final: bool,
+        start: f64,
+        end: f64,
+        transcript: &'a str,
+        confidence: Option<f64>,
+        /// Speaker of the first word, when diarized.
+        speaker: Option<u32>,
+        words: &'a [Word],
+    },
+    SpeechStarted {
+        connection: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        channel_label: Option<&'a str>,
+        timestamp: Option<f64>,
+    },
+    UtteranceEnd {
+        connection: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        channel_label: Option<&'a str>,
+        last_word_end: Option<f64>,
+    },
+}
+
+/// Publishes transcript events to every client of the `--serve` server.
+#[derive(Clone, Debug)]
+pub(crate) struct TranscriptServer {
+    events: broadcast::Sender<String>,
+}
+
+impl TranscriptServer {
+    /// Bind `addr` and serve, in the background:
+    ///
+    /// - `/` — a caption page for browser sources such as OBS
+    /// - `/events` — Server-Sent Events, one JSON event per `data:` line
+    /// - `/ws` — a WebSocket sending one JSON event per text message
+    pub(crate) async fn start(addr: &str) -> std::io::Result<(Self, SocketAddr)> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let server = TranscriptServer { events };
+
+        let accept_server = server.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let server = accept_server.clone();
+                tokio::spawn(async move {
+                    let _ = server.handle_connection(stream).await;
+                });
+            }
+        });
+        Ok((server, local_addr))
+    }
+
+    pub(crate) fn publish(&self, event: &ServeEvent) {
+        if self.events.receiver_count() == 0 {
+            return;
+        }
+        if let Ok(json) = serde_json::to_string(event) {
+            let _ = self.events.send(json);
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let request = read_request_head(&mut stream).await?;
+        if let Some(key) = &request.websocket_key {
+            // Subscribe before completing the handshake, so no event published
+            // after the client sees the upgrade is missed.
+            let events = self.events.subscribe();
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                derive_accept_key(key.as_bytes())
+            );
+            stream.write_all(response.as_bytes()).await?;
+            let websocket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+            serve_websocket(websocket, events).await;
+            return Ok(());
+        }
+
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/events") => {
+                let events = self.events.subscribe();
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\nConnection: keep-alive\r\n\r\n",
+                    )
+                    .await?;
+                serve_events(stream, events).await
+            }
+            ("GET", "/" | "/index.html") => {
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    CAPTIONS_PAGE.len(),
+                    CAPTIONS_PAGE
+                );
+                stream.write_all(response.as_bytes()).await
+            }
+            _ => {
+                stream
+                    .write_all(
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+struct RequestHead {
+    method: String,
+    path: String,
+    /// `Sec-WebSocket-Key` of a WebSocket upgrade request.
+    websocket_key: Option<String>,
+}
+
+/// Read the request line and headers. Clients send nothing more until they
+/// get a response, so no body or WebSocket frames are consumed.
+async fn read_request_head(stream: &mut TcpStream) -> std::io::Result<RequestHead> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    // Ignore any query string, e.g. the caption page's ?lines=3.
+    let path = parts
+        .next()
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    let mut upgrade = false;
+    let mut websocket_key = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("upgrade") && value.eq_ignore_ascii_case("websocket") {
+                upgrade = true;
+            } else if name.eq_ignore_ascii_case("sec-websocket-key") {
+                websocket_key = Some(value.to_string());
+            }
+        }
+    }
+
+    Ok(RequestHead {
+        method,
+        path,
+        websocket_key: websocket_key.filter(|_| upgrade),
+    })
+}
+
+/// Forward events to a WebSocket client until either side goes away.
+async fn serve_websocket(
+    websocket: WebSocketStream<TcpStream>,
+    mut events: broadcast::Receiver<String>,
+) {
+    let (mut sink, mut incoming) = websocket.split();
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(json) => {
+                    if sink.send(Message::text(json)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    return;
+                }
+            },
+            // Clients have nothing to say; read only to notice them leaving.
+            message = incoming.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Forward events to a Server-Sent Events client until it disconnects.
+async fn serve_events(
+    mut stream: TcpStream,
+    mut events: broadcast::Receiver<String>,
+) -> std::io::Result<()> {
+    loop {
+        let chunk = match tokio::time::timeout(SSE_KEEPALIVE, events.recv()).await {
+            Ok(Ok(json)) => format!("data: {json}\n\n"),
+            Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+            Ok(Err(broadcast::error::RecvError::Closed)) => return Ok(()),
+            Err(_) => ": keep-alive\n\n".to_string(),
+        };
+        stream.write_all(chunk.as_bytes()).await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ServeEvent, TranscriptServer};
+    use futures_util::StreamExt;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpStream;
+
+    fn final_result(transcript: &str) -> ServeEvent<'_> {
+        ServeEvent::Transcript {
+            connection: 1,
+            channel_label: None,
+            request_id: Some("req-1"),
+            is_final: true,
+            speech_final: true,
+            start: 1.5,
+            end: 2.25,
+            transcript,
+            confidence: Some(0.9),
+            speaker: None,
+            words: &[],
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_caption_page_sse_and_websocket_events() {
+        let (server, addr) = TranscriptServer::start("127.0.0.1:0").await.unwrap();
+
+        let mut page = TcpStream::connect(addr).await.unwrap();
+        page.write_all(b"GET /?lines=3 HTTP/1.1\r\nHost: x\r\n\r\n")
+            .await
+            .unwrap();
+        let mut html = String::new();
+        page.read_to_string(&mut html).await.unwrap();
+        assert!(html.starts_with("HTTP/1.1 200 OK"));
+        assert!(html.contains("new EventSource(\"/events\")"));
+
+        let sse = TcpStream::connect(addr).await.unwrap();
+        let mut sse = BufReader::new(sse);
+        sse.get_mut()
+            .write_all(b"GET /events HTTP/1.1\r\nHost: x\r\n\r\n")
+            .await
+            .unwrap();
+        let mut line = String::new();
+        while line != "\r\n" {
+            line.clear();
+            sse.read_line(&mut line).await.unwrap();
+        }
+
+        let (mut websocket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+
+        server.publish(&final_result("hello world"));
+
+        let expected = r#"{"type":"transcript","connection":1,"request_id":"req-1","is_final":true,"speech_final":true,"start":1.5,"end":2.25,"transcript":"hello world","confidence":0.9,"speaker":null,"words":[]}"#;
+        line.clear();
+        sse.read_line(&mut line).await.unwrap();
+        assert_eq!(line, format!("data: {expected}\n"));
+        let message = websocket.next().await.unwrap().unwrap();
+        assert_eq!(message.to_text().unwrap(), expected);
+    }
+}
@@ -1,110 +1,267 @@
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 use std::path::PathBuf;
 
+use crate::devices::InputDeviceArgs;
+use crate::latency::LatencyCollector;
+use crate::protocol::DeepgramClientConfig;
+use crate::query::parse_query_param;
+use crate::subtitles::SubtitleArgs;
+
+#[derive(Args, Clone, Debug)]
+pub struct ReconnectArgs {
+    /// Reconnect if the WebSocket drops mid-stream, replaying recently sent audio.
+    /// A connection lost after CloseStream counts as a drop only if no final
+    /// result arrived after the last audio was sent
+    #[arg(long)]
+    pub reconnect: bool,
+
+    /// Maximum reconnect attempts after each dropped connection
+    #[arg(long, default_value_t = 5)]
+    pub reconnect_attempts: u32,
+
+    /// Initial delay in milliseconds before reconnecting; doubles after each failed attempt
+    #[arg(long, default_value_t = 500)]
+    pub reconnect_backoff: u64,
+
+    /// Seconds of recently sent audio to replay on the new connection
+    #[arg(long, default_value_t = 5.0)]
+    pub replay_seconds: f64,
+}
+
+/// Options shared by every streaming source.
+#[derive(Args, Clone, Debug)]
+pub struct StreamOptions {
+    /// Callback URL for receiving transcription results
+    #[arg(long)]
+    pub callback: Option<String>,
+
+    /// Suppress console output of transcripts
+    #[arg(long)]
+    pub silent: bool,
+
+    /// Override the Deepgram API base URL
+    #[arg(long)]
+    pub endpoint: Option<String>,
+
+    /// Number of parallel Deepgram streaming connections to open with the same input audio
+    #[arg(long, default_value_t = 1)]
+    pub connections: usize,
+
+    /// Stagger connection starts evenly over this many seconds instead of opening them
+    /// all at once. Connections that start later join the audio where it is.
+    #[arg(long)]
+    pub ramp_up: Option<f64>,
+
+    /// Send each channel of the audio as mono to its own connection instead of sending
+    /// interleaved audio, e.g. to compare against --multichannel
+    #[arg(long, conflicts_with_all = ["connections", "multichannel"])]
+    pub split_channels: bool,
+
+    /// Comma-separated labels for the channels with --split-channels (e.g. "agent,customer")
+    #[arg(long, requires = "split_channels")]
+    pub channel_labels: Option<String>,
+
+    /// Close every connection after this many seconds
+    #[arg(long)]
+    pub duration: Option<f64>,
+
+    /// Audio encoding format (e.g., linear16, mulaw, flac)
+    #[arg(long)]
+    pub encoding: Option<String>,
+
+    /// Audio sample rate in Hz
+    #[arg(long)]
+    pub sample_rate: Option<u32>,
+
+    /// Number of audio channels
+    #[arg(long)]
+    pub channels: Option<u16>,
+
+    /// Resample the audio to this rate in Hz before sending it, with a low-pass filter
+    /// when downsampling (e.g. 8000 to test telephony conditions)
+    #[arg(long)]
+    pub target_sample_rate: Option<u32>,
+
+    /// Downmix the audio to a single channel before sending it
+    #[arg(long, conflicts_with_all = ["split_channels", "multichannel"])]
+    pub mono: bool,
+
+    /// Enable multichannel processing
+    #[arg(long)]
+    pub multichannel: bool,
+
+    /// Enable speaker diarization (identify individual speakers)
+    #[arg(long)]
+    pub diarize: bool,
+
+    /// Detect named entities (people, places, organizations, etc.)
+    #[arg(long)]
+    pub detect_entities: bool,
+
+    /// Enable interim results
+    #[arg(long)]
+    pub interim_results: bool,
+
+    /// Enable voice activity detection events
+    #[arg(long)]
+    pub vad_events: bool,
+
+    /// Enable punctuation
+    #[arg(long)]
+    pub punctuate: bool,
+
+    /// Enable smart formatting
+    #[arg(long)]
+    pub smart_format: bool,
+
+    /// Enable sentiment analysis
+    #[arg(long)]
+    pub sentiment: bool,
+
+    /// Enable intent recognition
+    #[arg(long)]
+    pub intents: bool,
+
+    /// Enable topic detection
+    #[arg(long)]
+    pub topics: bool,
+
+    /// Deepgram model to use (e.g., nova-2, enhanced, base)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Model version to use (for example, a Nova-3 model version)
+    #[arg(long)]
+    pub version: Option<String>,
+
+    /// Redact entities (comma-separated). Can include specific entities or categories: phi, pii, pci, other
+    #[arg(long)]
+    pub redact: Option<String>,
+
+    /// Language code for transcription (e.g., en, es, fr, de), or multi for
+    /// conversations that switch between languages. multi defaults --model to nova-3,
+    /// and final results tag each change of language, e.g. "[es] hola [en] hello"
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Endpointing sensitivity in milliseconds (e.g., 10, 300, 500). Controls how long
+    /// Deepgram waits after speech stops before finalizing a transcript segment.
+    /// Lower values produce faster but potentially incomplete results.
+    #[arg(long)]
+    pub endpointing: Option<u32>,
+
+    /// Utterance end timeout in milliseconds (e.g., 1000). Deepgram sends an UtteranceEnd
+    /// message after this many ms of silence, signaling the end of an utterance.
+    /// Requires --interim-results.
+    #[arg(long)]
+    pub utterance_end: Option<u32>,
+
+    /// Comma-separated keyterms to boost recognition for (nova-3+ only, e.g. --keyterm "Deepgram,nova-3,speech AI")
+    #[arg(long, conflicts_with = "keywords")]
+    pub keyterm: Option<String>,
+
+    /// Comma-separated keywords to boost recognition for (nova-2 and older, optional intensifier per word, e.g. --keywords "Deepgram:2,API,speech:-1")
+    #[arg(long, conflicts_with = "keyterm")]
+    pub keywords: Option<String>,
+
+    /// Extra query parameter sent as-is, for Deepgram options without a dedicated flag.
+    /// Repeatable, e.g. --param filler_words=true --param tag=load-test
+    #[arg(long = "param", value_name = "KEY=VALUE", value_parser = parse_query_param)]
+    pub params: Vec<(String, String)>,
+
+    /// Print the WebSocket URL and headers for the audio's format instead of connecting
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Write finalized results to an SRT or WebVTT subtitle file (format chosen by the
+    /// .srt or .vtt extension). With --connections, each connection gets its own file.
+    #[arg(long)]
+    pub subtitles: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub subtitle_options: SubtitleArgs,
+
+    /// Append one JSON line per finalized result to this file. With --connections, all
+    /// connections share the file and each line records its connection number.
+    #[arg(long)]
+    pub transcript_log: Option<PathBuf>,
+
+    /// Save the audio sent to Deepgram to this WAV file, at the sample rate and channel
+    /// count in the query, with a .json sidecar holding the request IDs and a command
+    /// that replays the session with `stream file`
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Republish transcripts on this local address (e.g. 127.0.0.1:8765): a caption
+    /// page at /, Server-Sent Events at /events, and a WebSocket at /ws
+    #[arg(long, value_name = "ADDR")]
+    pub serve: Option<String>,
+
+    /// Measure time to first transcript and final-result lag per connection, and
+    /// print a percentile table when the stream ends
+    #[arg(long)]
+    pub latency: bool,
+
+    /// Also write the latency table to this CSV file (implies --latency)
+    #[arg(long)]
+    pub latency_csv: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub reconnect: ReconnectArgs,
+}
+
+impl StreamOptions {
+    pub(crate) fn client_config(self, api_key: Option<String>) -> DeepgramClientConfig {
+        DeepgramClientConfig {
+            api_key,
+            callback: self.callback,
+            silent: self.silent,
+            endpoint: self.endpoint,
+            encoding: self.encoding,
+            sample_rate_override: self.sample_rate,
+            channels_override: self.channels,
+            multichannel: self.multichannel,
+            diarize: self.diarize,
+            detect_entities: self.detect_entities,
+            interim_results: self.interim_results,
+            vad_events: self.vad_events,
+            punctuate: self.punctuate,
+            smart_format: self.smart_format,
+            sentiment: self.sentiment,
+            intents: self.intents,
+            topics: self.topics,
+            model: self.model,
+            version: self.version,
+            redact: self.redact,
+            language: self.language,
+            endpointing: self.endpointing,
+            utterance_end: self.utterance_end,
+            keyterm: self.keyterm,
+            keywords: self.keywords,
+            extra_params: self.params,
+            subtitles: self.subtitles,
+            subtitle_options: self.subtitle_options,
+            reconnect: self.reconnect,
+            transcript_log: self.transcript_log,
+            ramp_up: self.ramp_up,
+            channel_labels: Vec::new(),
+            recording: None,
+            server: None,
+            latency: (self.latency || self.latency_csv.is_some())
+                .then(|| LatencyCollector::new(self.latency_csv)),
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum StreamSource {
     /// Stream audio from microphone for real-time transcription
     Microphone {
-        /// Callback URL for receiving transcription results
-        #[arg(long)]
-        callback: Option<String>,
-
-        /// Suppress console output of transcripts
-        #[arg(long)]
-        silent: bool,
-
-        /// Override the Deepgram API base URL
-        #[arg(long)]
-        endpoint: Option<String>,
-
-        /// Number of parallel Deepgram streaming connections to open with the same input audio
-        #[arg(long, default_value_t = 1)]
-        connections: usize,
-
-        /// Audio encoding format (e.g., linear16, mulaw, flac)
-        #[arg(long)]
-        encoding: Option<String>,
-
-        /// Audio sample rate in Hz
-        #[arg(long)]
-        sample_rate: Option<u32>,
-
-        /// Number of audio channels
-        #[arg(long)]
-        channels: Option<u16>,
-
-        /// Enable multichannel processing
-        #[arg(long)]
-        multichannel: bool,
-
-        /// Enable speaker diarization (identify individual speakers)
-        #[arg(long)]
-        diarize: bool,
-
-        /// Detect named entities (people, places, organizations, etc.)
-        #[arg(long)]
-        detect_entities: bool,
+        #[command(flatten)]
+        input: InputDeviceArgs,
 
-        /// Enable interim results
-        #[arg(long)]
-        interim_results: bool,
-
-        /// Enable voice activity detection events
-        #[arg(long)]
-        vad_events: bool,
-
-        /// Enable punctuation
-        #[arg(long)]
-        punctuate: bool,
-
-        /// Enable smart formatting
-        #[arg(long)]
-        smart_format: bool,
-
-        /// Enable sentiment analysis
-        #[arg(long)]
-        sentiment: bool,
-
-        /// Enable intent recognition
-        #[arg(long)]
-        intents: bool,
-
-        /// Enable topic detection
-        #[arg(long)]
-        topics: bool,
-
-        /// Deepgram model to use (e.g., nova-2, enhanced, base)
-        #[arg(long)]
-        model: Option<String>,
-
-        /// Model version to use (for example, a Nova-3 model version)
-        #[arg(long)]
-        version: Option<String>,
-
-        /// Redact entities (comma-separated). Can include specific entities or categories: phi, pii, pci, other
-        #[arg(long)]
-        redact: Option<String>,
-
-        /// Language code for transcription (e.g., en, es, fr, de)
-        #[arg(long)]
-        language: Option<String>,
-
-        /// Time in milliseconds of silence before Deepgram finalizes a transcript segment.
-        #[arg(long)]
-        endpointing: Option<u32>,
-
-        /// Time in milliseconds of silence after the last finalized word before Deepgram
-        /// sends an UtteranceEnd message. Requires --interim-results.
-        #[arg(long)]
-        utterance_end: Option<u32>,
-
-        /// Comma-separated keyterms to boost recognition for (nova-3+ only, e.g. --keyterm "Deepgram,nova-3,speech AI")
-        #[arg(long, conflicts_with = "keywords")]
-        keyterm: Option<String>,
-
-        /// Comma-separated keywords to boost recognition for (nova-2 and older, optional intensifier per word, e.g. --keywords "Deepgram:2,API,speech:-1")
-        #[arg(long, conflicts_with = "keyterm")]
-        keywords: Option<String>,
+        #[command(flatten)]
+        options: StreamOptions,
     },
     /// Stream audio from a file for transcription
     File {
@@ -116,108 +273,49 @@ pub enum StreamSource {
         #[arg(long)]
         fast: bool,
 
-        /// Callback URL for receiving transcription results
-        #[arg(long)]
-        callback: Option<String>,
-
-        /// Suppress console output of transcripts
-        #[arg(long)]
-        silent: bool,
-
-        /// Override the Deepgram API base URL
-        #[arg(long)]
-        endpoint: Option<String>,
-
-        /// Number of parallel Deepgram streaming connections to open with the same input audio
-        #[arg(long, default_value_t = 1)]
-        connections: usize,
-
-        /// Audio encoding format (e.g., linear16, mulaw, flac)
-        #[arg(long)]
-        encoding: Option<String>,
-
-        /// Audio sample rate in Hz
-        #[arg(long)]
-        sample_rate: Option<u32>,
-
-        /// Number of audio channels
-        #[arg(long)]
-        channels: Option<u16>,
-
-        /// Enable multichannel processing
-        #[arg(long)]
-        multichannel: bool,
-
-        /// Enable speaker diarization (identify individual speakers)
-        #[arg(long)]
-        diarize: bool,
-
-        /// Detect named entities (people, places, organizations, etc.)
-        #[arg(long)]
-        detect_entities: bool,
-
-        /// Enable interim results
-        #[arg(long)]
-        interim_results: bool,
-
-        /// Enable voice activity detection events
-        #[arg(long)]
-        vad_events: bool,
-
-        /// Enable punctuation
-        #[arg(long)]
-        punctuate: bool,
-
-        /// Enable smart formatting
-        #[arg(long)]
-        smart_format: bool,
-
-        /// Enable sentiment analysis
-        #[arg(long)]
-        sentiment: bool,
+        /// Start the file over when it ends, streaming continuously until --duration
+        /// elapses or Ctrl+C is pressed
+        #[arg(long = "loop")]
+        looping: bool,
 
-        /// Enable intent recognition
-        #[arg(long)]
-        intents: bool,
-
-        /// Enable topic detection
-        #[arg(long)]
-        topics: bool,
-
-        /// Deepgram model to use (e.g., nova-2, enhanced, base)
-        #[arg(long)]
-        model: Option<String>,
-
-        /// Model version to use (for example, a Nova-3 model version)
-        #[arg(long)]
-        version: Option<String>,
+        #[command(flatten)]
+        options: StreamOptions,
+    },
+    /// Stream audio piped to stdin, e.g. from ffmpeg, sox, or a named pipe
+    Stdin {
+        /// Forward stdin bytes as-is instead of decoding a container format.
+        /// Requires --encoding and --sample-rate; --channels defaults to 1.
+        #[arg(long, requires_all = ["encoding", "sample_rate"])]
+        raw: bool,
 
-        /// Redact entities (comma-separated). Can include specific entities or categories: phi, pii, pci, other
+        /// Send audio as fast as it can be read instead of pacing it to real time
         #[arg(long)]
-        redact: Option<String>,
+        fast: bool,
 
-        /// Language code for transcription (e.g., en, es, fr, de)
-        #[arg(long)]
-        language: Option<String>,
+        #[command(flatten)]
+        options: StreamOptions,
+    },
+    /// Stream audio from an HTTP(S) file, Icecast/SHOUTcast stream, or HLS playlist
+    Url {
+        /// URL of the audio file, stream, or .m3u8 playlist
+        #[arg(short, long)]
+        url: String,
 
-        /// Endpointing sensitivity in milliseconds (e.g., 10, 300, 500). Controls how long
-        /// Deepgram waits after speech stops before finalizing a transcript segment.
-        /// Lower values produce faster but potentially incomplete results.
+        /// Send audio as fast as it downloads instead of pacing it to real time
         #[arg(long)]
-        endpointing: Option<u32>,
+        fast: bool,
 
-        /// Utterance end timeout in milliseconds (e.g., 1000). Deepgram sends an UtteranceEnd
-        /// message after this many ms of silence, signaling the end of an utterance.
-        /// Requires --interim-results.
-        #[arg(long)]
-        utterance_end: Option<u32>,
+        /// Maximum attempts to reconnect to the audio source after it drops. Independent
+        /// of --reconnect, which covers the Deepgram connection.
+        #[arg(long, default_value_t = 10)]
+        source_reconnect_attempts: u32,
 
-        /// Comma-separated keyterms to boost recognition for (nova-3+ only, e.g. --keyterm "Deepgram,nova-3,speech AI")
-        #[arg(long, conflicts_with = "keywords")]
-        keyterm: Option<String>,
+        /// Initial delay in milliseconds before reconnecting to the audio source;
+        /// doubles after each failed attempt
+        #[arg(long, default_value_t = 1000)]
+        source_reconnect_backoff: u64,
 
-        /// Comma-separated keywords to boost recognition for (nova-2 and older, optional intensifier per word, e.g. --keywords "Deepgram:2,API,speech:-1")
-        #[arg(long, conflicts_with = "keyterm")]
-        keywords: Option<String>,
+        #[command(flatten)]
+        options: StreamOptions,
     },
 }
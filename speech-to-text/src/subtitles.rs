@@ -0,0 +1,352 @@
+use clap::Args;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Clone, Debug)]
+pub struct SubtitleArgs {
+    /// Maximum characters per subtitle line (cues wrap onto at most two lines)
+    #[arg(long, default_value_t = 42)]
+    pub max_line_length: usize,
+
+    /// Maximum duration of a single subtitle cue in seconds
+    #[arg(long, default_value_t = 7.0)]
+    pub max_cue_duration: f64,
+
+    /// Prefix cues with "Speaker N:" labels when diarization is enabled
+    #[arg(long)]
+    pub speaker_labels: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" | "webvtt" => Some(SubtitleFormat::Vtt),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+    }
+}
+
+/// Give each parallel connection its own subtitle file, e.g. `captions-2.srt`,
+/// or `captions-agent.srt` for a labelled channel.
+pub(crate) fn connection_subtitle_path(
+    path: &Path,
+    connection_id: usize,
+    connection_count: usize,
+    channel_label: Option<&str>,
+) -> PathBuf {
+    let suffix = match channel_label {
+        Some(label) => label
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect(),
+        None if connection_count <= 1 => return path.to_path_buf(),
+        None => connection_id.to_string(),
+    };
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("subtitles");
+    let file_name = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{stem}-{suffix}.{ext}"),
+        None => format!("{stem}-{suffix}"),
+    };
+    path.with_file_name(file_name)
+}
+
+/// A timed word used as input for cue building.
+pub(crate) struct CueWord<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) start: f64,
+    pub(crate) end: f64,
+    pub(crate) speaker: Option<u32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Cue {
+    pub(crate) start: f64,
+    pub(crate) end: f64,
+    pub(crate) lines: Vec<String>,
+}
+
+/// Group timed words into cues that respect the line length, cue duration and
+/// speaker boundaries from `options`.
+pub(crate) fn build_cues(words: &[CueWord], options: &SubtitleArgs) -> Vec<Cue> {
+    let max_line_length = options.max_line_length.max(1);
+    let mut cues = Vec::new();
+    let mut current: Vec<&CueWord> = Vec::new();
+
+    for word in words {
+        if let Some(first) = current.first() {
+            let speaker_changed = options.speaker_labels && first.speaker != word.speaker;
+            let too_long = word.end - first.start > options.max_cue_duration;
+            let mut texts: Vec<&str> = current.iter().map(|w| w.text).collect();
+            texts.push(word.text);
+            let too_wide = wrap_lines(
+                &cue_text(&texts, first.speaker, options.speaker_labels),
+                max_line_length,
+            )
+            .len()
+                > 2;
+
+            if speaker_changed || too_long || too_wide {
+                cues.push(finish_cue(&current, options));
+                current.clear();
+            }
+        }
+        current.push(word);
+    }
+
+    if !current.is_empty() {
+        cues.push(finish_cue(&current, options));
+    }
+
+    cues
+}
+
+fn finish_cue(words: &[&CueWord], options: &SubtitleArgs) -> Cue {
+    let speaker = words[0].speaker;
+    let texts: Vec<&str> = words.iter().map(|w| w.text).collect();
+    Cue {
+        start: words[0].start,
+        end: words[words.len() - 1].end,
+        lines: wrap_lines(
+            &cue_text(&texts, speaker, options.speaker_labels),
+            options.max_line_length.max(1),
+        ),
+    }
+}
+
+fn cue_text(words: &[&str], speaker: Option<u32>, speaker_labels: bool) -> String {
+    match speaker {
+        Some(speaker) if speaker_labels => format!("Speaker {}: {}", speaker, words.join(" ")),
+        _ => words.join(" "),
+    }
+}
+
+/// Greedily wrap text on word boundaries. Words longer than the limit are kept
+/// on their own line rather than split.
+fn wrap_lines(text: &str, max_line_length: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= max_line_length => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+fn format_timestamp(seconds: f64, format: SubtitleFormat) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let separator = match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::Vtt => '.',
+    };
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        total_ms / 3_600_000,
+        (total_ms / 60_000) % 60,
+        (total_ms / 1000) % 60,
+        separator,
+        total_ms % 1000
+    )
+}
+
+/// Render a single cue block, including the trailing blank line.
+pub(crate) fn render_cue(index: usize, cue: &Cue, format: SubtitleFormat) -> String {
+    let mut block = String::new();
+    if format == SubtitleFormat::Srt {
+        block.push_str(&format!("{}\n", index));
+    }
+    block.push_str(&format!(
+        "{} --> {}\n",
+        format_timestamp(cue.start, format),
+        format_timestamp(cue.end, format)
+    ));
+    for line in &cue.lines {
+        block.push_str(line);
+        block.push('\n');
+    }
+    block.push('\n');
+    block
+}
+
+pub(crate) fn render_cues(cues: &[Cue], format: SubtitleFormat) -> String {
+    let mut output = String::new();
+    if format == SubtitleFormat::Vtt {
+        output.push_str("WEBVTT\n\n");
+    }
+    for (idx, cue) in cues.iter().enumerate() {
+        output.push_str(&render_cue(idx + 1, cue, format));
+    }
+    output
+}
+
+/// Incrementally writes cues to a subtitle file as finalized results arrive.
+pub(crate) struct SubtitleWriter {
+    writer: BufWriter<File>,
+    format: SubtitleFormat,
+    options: SubtitleArgs,
+    next_index: usize,
+}
+
+impl SubtitleWriter {
+    pub(crate) fn create(
+        path: &Path,
+        format: SubtitleFormat,
+        options: SubtitleArgs,
+    ) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == SubtitleFormat::Vtt {
+            writer.write_all(b"WEBVTT\n\n")?;
+        }
+        writer.flush()?;
+        Ok(SubtitleWriter {
+            writer,
+            format,
+            options,
+            next_index: 1,
+        })
+    }
+
+    pub(crate) fn write_words(&mut self, words: &[CueWord]) -> std::io::Result<()> {
+        for cue in build_cues(words, &self.options) {
+            self.writer
+                .write_all(render_cue(self.next_index, &cue, self.format).as_bytes())?;
+            self.next_index += 1;
+        }
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Cue, CueWord, SubtitleArgs, SubtitleFormat, build_cues, connection_subtitle_path,
+        render_cues,
+    };
+
+    fn options(
+        max_line_length: usize,
+        max_cue_duration: f64,
+        speaker_labels: bool,
+    ) -> SubtitleArgs {
+        SubtitleArgs {
+            max_line_length,
+            max_cue_duration,
+            speaker_labels,
+        }
+    }
+
+    fn word(text: &str, start: f64, end: f64, speaker: Option<u32>) -> CueWord<'_> {
+        CueWord {
+            text,
+            start,
+            end,
+            speaker,
+        }
+    }
+
+    #[test]
+    fn renders_srt_and_vtt_timestamps() {
+        let cues = vec![Cue {
+            start: 3661.5,
+            end: 3662.25,
+            lines: vec!["Hello there.".to_string()],
+        }];
+
+        assert_eq!(
+            render_cues(&cues, SubtitleFormat::Srt),
+            "1\n01:01:01,500 --> 01:01:02,250\nHello there.\n\n"
+        );
+        assert_eq!(
+            render_cues(&cues, SubtitleFormat::Vtt),
+            "WEBVTT\n\n01:01:01.500 --> 01:01:02.250\nHello there.\n\n"
+        );
+    }
+
+    #[test]
+    fn splits_cues_on_duration_and_line_length() {
+        let words = [
+            word("one", 0.0, 0.5, None),
+            word("two", 0.5, 1.0, None),
+            word("three", 1.0, 2.5, None),
+            word("four", 2.5, 3.0, None),
+        ];
+
+        let by_duration = build_cues(&words, &options(42, 2.0, false));
+        assert_eq!(by_duration.len(), 2);
+        assert_eq!(by_duration[0].lines, vec!["one two"]);
+        assert_eq!(by_duration[1].start, 1.0);
+
+        let by_width = build_cues(&words, &options(7, 10.0, false));
+        assert_eq!(
+            by_width.iter().map(|c| c.lines.clone()).collect::<Vec<_>>(),
+            vec![vec!["one two", "three"], vec!["four"]]
+        );
+    }
+
+    #[test]
+    fn speaker_labels_start_a_new_cue_per_speaker() {
+        let words = [
+            word("Hi.", 0.0, 0.4, Some(0)),
+            word("Hello.", 0.5, 0.9, Some(1)),
+        ];
+
+        let cues = build_cues(&words, &options(42, 7.0, true));
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].lines, vec!["Speaker 0: Hi."]);
+        assert_eq!(cues[1].lines, vec!["Speaker 1: Hello."]);
+
+        let unlabeled = build_cues(&words, &options(42, 7.0, false));
+        assert_eq!(unlabeled[0].lines, vec!["Hi. Hello."]);
+    }
+
+    #[test]
+    fn parallel_connections_write_separate_subtitle_files() {
+        let path = std::path::Path::new("out/captions.srt");
+        assert_eq!(connection_subtitle_path(path, 1, 1, None), path);
+        assert_eq!(
+            connection_subtitle_path(path, 2, 3, None),
+            std::path::Path::new("out/captions-2.srt")
+        );
+        assert_eq!(
+            connection_subtitle_path(path, 1, 2, Some("front desk")),
+            std::path::Path::new("out/captions-front-desk.srt")
+        );
+    }
+
+    #[test]
+    fn subtitle_format_is_inferred_from_extension() {
+        assert_eq!(
+            SubtitleFormat::from_path(std::path::Path::new("captions.VTT")),
+            Some(SubtitleFormat::Vtt)
+        );
+        assert_eq!(
+            SubtitleFormat::from_path(std::path::Path::new("captions.srt")),
+            Some(SubtitleFormat::Srt)
+        );
+        assert_eq!(
+            SubtitleFormat::from_path(std::path::Path::new("captions.txt")),
+            None
+        );
+    }
+}
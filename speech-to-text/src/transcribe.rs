@@ -1,22 +1,66 @@
 use clap::Args;
 use serde::Deserialize;
+use std::fmt::Write;
 use std::path::PathBuf;
+use std::time::Duration;
 use urlencoding;
 
+use crate::callback::CallbackReceiver;
+use crate::query::{parse_query_param, print_dry_run, push_extra_params, redacted_authorization};
+use crate::subtitles::{CueWord, SubtitleArgs, SubtitleFormat, build_cues, render_cues};
+
 #[derive(Args)]
 pub struct TranscribeArgs {
     /// Path to the audio file
-    #[arg(short, long)]
-    pub file: PathBuf,
+    #[arg(short, long, required_unless_present = "url", conflicts_with = "url")]
+    pub file: Option<PathBuf>,
+
+    /// URL of a hosted audio file for Deepgram to fetch instead of uploading local bytes
+    #[arg(long)]
+    pub url: Option<String>,
+
+    /// Callback URL that Deepgram POSTs the result to instead of returning it in the response
+    #[arg(long)]
+    pub callback: Option<String>,
+
+    /// Run a local HTTP receiver on this address (e.g. 127.0.0.1:8000) and wait for the callback.
+    /// Without --callback, the callback URL defaults to http://<ADDR>/callback
+    #[arg(long)]
+    pub listen_callback: Option<String>,
+
+    /// Seconds to wait for the callback when using --listen-callback
+    #[arg(long, default_value_t = 600)]
+    pub callback_timeout: u64,
+
+    /// Print the request URL and headers instead of sending the request
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub options: TranscribeOptions,
+}
 
+/// Query and output options shared by `transcribe` and `transcribe-batch`.
+#[derive(Args, Clone)]
+pub struct TranscribeOptions {
     /// Deepgram model to use (e.g., nova-3, nova-2, enhanced, base)
     #[arg(long)]
     pub model: Option<String>,
 
-    /// Language code for transcription (e.g., en, es, fr, de)
+    /// Model version to use (for example, a Nova-3 model version)
+    #[arg(long)]
+    pub version: Option<String>,
+
+    /// Language code for transcription (e.g., en, es, fr, de), or multi for
+    /// conversations that switch between languages (nova-3 and nova-2)
     #[arg(long)]
     pub language: Option<String>,
 
+    /// Detect the dominant language of each channel instead of passing --language,
+    /// optionally choosing only among comma-separated candidates (e.g. --detect-language en,es)
+    #[arg(long, value_name = "LANGS", num_args = 0..=1, conflicts_with = "language")]
+    pub detect_language: Option<Option<String>>,
+
     /// Enable punctuation
     #[arg(long)]
     pub punctuate: Option<bool>,
@@ -61,10 +105,13 @@ pub struct TranscribeArgs {
     #[arg(long)]
     pub encoding: Option<String>,
 
-    /// Output format (json, verbose-json, or text)
+    /// Output format (json, verbose-json, text, srt, or vtt)
     #[arg(long, default_value = "text")]
     pub output: String,
 
+    #[command(flatten)]
+    pub subtitles: SubtitleArgs,
+
     /// Override the Deepgram API base URL
     #[arg(long)]
     pub endpoint: Option<String>,
@@ -76,6 +123,31 @@ pub struct TranscribeArgs {
     /// Comma-separated keywords to boost recognition for (nova-2 and older, optional intensifier per word, e.g. --keywords "Deepgram:2,API,speech:-1")
     #[arg(long, conflicts_with = "keyterm")]
     pub keywords: Option<String>,
+
+    /// Extra query parameter sent as-is, for Deepgram options without a dedicated flag.
+    /// Repeatable, e.g. --param filler_words=true --param numerals=true
+    #[arg(long = "param", value_name = "KEY=VALUE", value_parser = parse_query_param)]
+    pub params: Vec<(String, String)>,
+
+    /// Split the transcript into utterances (speech separated by pauses) and show them
+    /// one per line with timestamps and speaker labels in text output
+    #[arg(long)]
+    pub utterances: bool,
+
+    /// Split the transcript into paragraphs and show them with timestamps and speaker
+    /// labels in text output
+    #[arg(long)]
+    pub paragraphs: bool,
+
+    /// Search the audio for a term or phrase; hits are listed with timestamps in text
+    /// output. Repeatable, e.g. --search "cancel my account" --search refund
+    #[arg(long, value_name = "TERM")]
+    pub search: Vec<String>,
+
+    /// Replace a term in the transcript, given as FROM:TO (an empty TO removes it).
+    /// Repeatable, e.g. --replace "acme:Acme" --replace "um:"
+    #[arg(long, value_name = "FROM:TO", value_parser = parse_replacement)]
+    pub replace: Vec<String>,
 }
 
 // Response structures for pre-recorded API
@@ -99,11 +171,46 @@ struct PreRecordedResults {
     channels: Vec<PreRecordedChannel>,
     #[serde(default)]
     summary: Option<PreRecordedSummary>,
+    #[serde(default)]
+    utterances: Vec<PreRecordedUtterance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreRecordedUtterance {
+    start: f64,
+    end: f64,
+    transcript: String,
+    #[serde(default)]
+    channel: usize,
+    #[serde(default)]
+    speaker: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
 struct PreRecordedChannel {
     alternatives: Vec<PreRecordedAlternative>,
+    #[serde(default)]
+    detected_language: Option<String>,
+    #[serde(default)]
+    language_confidence: Option<f64>,
+    #[serde(default)]
+    search: Vec<PreRecordedSearch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreRecordedSearch {
+    query: String,
+    #[serde(default)]
+    hits: Vec<PreRecordedHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreRecordedHit {
+    confidence: f64,
+    start: f64,
+    end: f64,
+    #[serde(default)]
+    snippet: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -113,6 +220,9 @@ struct PreRecordedAlternative {
     confidence: f64,
     #[serde(default)]
     words: Vec<PreRecordedWord>,
+    /// Languages spoken in the transcript, with `language=multi`.
+    #[serde(default)]
+    languages: Vec<String>,
     #[serde(default)]
     paragraphs: Option<PreRecordedParagraphs>,
     #[serde(default)]
@@ -137,6 +247,8 @@ struct PreRecordedWord {
     speaker: Option<u32>,
     #[serde(default)]
     punctuated_word: Option<String>,
+    #[serde(default)]
+    language: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -147,6 +259,8 @@ struct PreRecordedParagraphs {
 #[derive(Debug, Deserialize)]
 struct PreRecordedParagraph {
     sentences: Vec<PreRecordedSentence>,
+    #[serde(default)]
+    speaker: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -216,93 +330,207 @@ fn parse_redact_entities(redact_value: &str) -> Vec<String> {
     entities
 }
 
-pub async fn run_transcribe_mode(
-    api_key: Option<String>,
-    args: TranscribeArgs,
-) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Transcribing audio file: {}", args.file.display());
+/// Validate a `--replace FROM:TO` value. Only the first `:` separates the two,
+/// so the replacement may contain colons.
+fn parse_replacement(arg: &str) -> Result<String, String> {
+    match arg.split_once(':') {
+        Some((from, _)) if !from.trim().is_empty() => Ok(arg.to_string()),
+        _ => Err(format!("expected FROM:TO, got {arg:?}")),
+    }
+}
+
+/// `detect_language` query parameters: `true`, or one per comma-separated
+/// candidate. A profile's `detect_language = true` arrives as the value "true".
+fn detect_language_params(candidates: Option<&str>) -> Vec<String> {
+    let candidates: Vec<&str> = candidates
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|candidate| !candidate.is_empty() && *candidate != "true")
+        .collect();
+    if candidates.is_empty() {
+        return vec!["detect_language=true".to_string()];
+    }
+    candidates
+        .iter()
+        .map(|candidate| format!("detect_language={}", urlencoding::encode(candidate)))
+        .collect()
+}
+
+/// A run of consecutive words in one language.
+struct LanguageSegment<'a> {
+    language: &'a str,
+    start: f64,
+    end: f64,
+    words: Vec<&'a str>,
+}
+
+/// Group words into runs by their `language`, as returned with
+/// `language=multi`. Words without a language stay in the current run; any
+/// before the first tagged word open the first run, which takes that word's
+/// language.
+fn language_segments(words: &[PreRecordedWord]) -> Vec<LanguageSegment<'_>> {
+    let mut segments: Vec<LanguageSegment> = Vec::new();
+    for word in words {
+        let text = word.punctuated_word.as_deref().unwrap_or(&word.word);
+        let language = word.language.as_deref();
+        match segments.last_mut() {
+            Some(last)
+                if language.is_none_or(|language| {
+                    last.language.is_empty() || language == last.language
+                }) =>
+            {
+                if last.language.is_empty()
+                    && let Some(language) = language
+                {
+                    last.language = language;
+                }
+                last.end = word.end;
+                last.words.push(text);
+            }
+            _ => segments.push(LanguageSegment {
+                language: language.unwrap_or_default(),
+                start: word.start,
+                end: word.end,
+                words: vec![text],
+            }),
+        }
+    }
+    segments
+}
+
+/// `mm:ss.s`, or `h:mm:ss.s` from an hour on, for offsets in text output.
+fn format_offset(seconds: f64) -> String {
+    let tenths = (seconds.max(0.0) * 10.0).round() as u64;
+    let (hours, minutes, secs) = (
+        tenths / 36_000,
+        (tenths / 600) % 60,
+        (tenths % 600) as f64 / 10.0,
+    );
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:04.1}")
+    } else {
+        format!("{minutes:02}:{secs:04.1}")
+    }
+}
 
-    // Read the audio file
-    let audio_data = std::fs::read(&args.file)?;
-    println!("Read {} bytes from file", audio_data.len());
+/// Build subtitle cues from the first alternative of every channel. Cues from
+/// multiple channels are interleaved by start time.
+fn render_subtitles(
+    results: &PreRecordedResults,
+    options: &SubtitleArgs,
+    format: SubtitleFormat,
+) -> String {
+    let mut cues = Vec::new();
+    for channel in &results.channels {
+        if let Some(alternative) = channel.alternatives.first() {
+            let words: Vec<CueWord> = alternative
+                .words
+                .iter()
+                .map(|word| CueWord {
+                    text: word.punctuated_word.as_deref().unwrap_or(&word.word),
+                    start: word.start,
+                    end: word.end,
+                    speaker: word.speaker,
+                })
+                .collect();
+            cues.extend(build_cues(&words, options));
+        }
+    }
+    cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+    render_cues(&cues, format)
+}
 
+/// Build the pre-recorded `/v1/listen` URL for the requested options.
+pub(crate) fn build_listen_url(options: &TranscribeOptions, callback: Option<&str>) -> String {
     // Build the API URL with query parameters
-    let base_url = args
+    let base_url = options
         .endpoint
+        .clone()
         .unwrap_or_else(|| "https://api.deepgram.com".to_string());
     let mut url = format!("{}/v1/listen?", base_url);
     let mut params = Vec::new();
 
     // Add model parameter
-    if let Some(model_name) = args.model {
+    if let Some(model_name) = &options.model {
         params.push(format!("model={}", model_name));
     }
 
+    // Add model version parameter
+    if let Some(version) = &options.version {
+        params.push(format!("version={}", urlencoding::encode(version)));
+    }
+
     // Add language parameter
-    if let Some(lang) = args.language {
+    if let Some(lang) = &options.language {
         params.push(format!("language={}", lang));
     }
 
+    // Add detect_language parameters: true, or one parameter per candidate
+    if let Some(candidates) = &options.detect_language {
+        params.extend(detect_language_params(candidates.as_deref()));
+    }
+
     // Add punctuate parameter
-    if let Some(punct) = args.punctuate {
+    if let Some(punct) = &options.punctuate {
         params.push(format!("punctuate={}", punct));
     }
 
     // Add smart_format parameter
-    if let Some(smart) = args.smart_format {
+    if let Some(smart) = &options.smart_format {
         params.push(format!("smart_format={}", smart));
     }
 
     // diarize_model enables diarization; omit it when diarization is disabled.
-    if args.diarize == Some(true) {
+    if options.diarize == Some(true) {
         params.push("diarize_model=latest".to_string());
     }
 
     // Add multichannel parameter
-    if args.multichannel {
+    if options.multichannel {
         params.push("multichannel=true".to_string());
     }
 
     // Add sentiment parameter
-    if let Some(sent) = args.sentiment {
+    if let Some(sent) = &options.sentiment {
         params.push(format!("sentiment={}", sent));
     }
 
     // Add summarize parameter
-    if let Some(summ) = args.summarize {
+    if let Some(summ) = &options.summarize {
         params.push(format!("summarize={}", summ));
     }
 
     // Add topics parameter
-    if let Some(top) = args.topics {
+    if let Some(top) = &options.topics {
         params.push(format!("topics={}", top));
     }
 
     // Add intents parameter
-    if let Some(int) = args.intents {
+    if let Some(int) = &options.intents {
         params.push(format!("intents={}", int));
     }
 
     // Add detect_entities parameter
-    if let Some(ent) = args.detect_entities {
+    if let Some(ent) = &options.detect_entities {
         params.push(format!("detect_entities={}", ent));
     }
 
     // Add redact parameter
-    if let Some(redact_value) = args.redact {
-        let redact_entities = parse_redact_entities(&redact_value);
+    if let Some(redact_value) = &options.redact {
+        let redact_entities = parse_redact_entities(redact_value);
         if !redact_entities.is_empty() {
             params.push(format!("redact={}", redact_entities.join("&redact=")));
         }
     }
 
     // Add encoding parameter
-    if let Some(enc) = args.encoding {
+    if let Some(enc) = &options.encoding {
         params.push(format!("encoding={}", enc));
     }
 
     // Add keyterm parameters if specified (each term becomes a separate keyterm= param)
-    if let Some(keyterms) = args.keyterm {
+    if let Some(keyterms) = &options.keyterm {
         for term in keyterms.split(',').map(str::trim).filter(|s| !s.is_empty()) {
             params.push(format!("keyterm={}", urlencoding::encode(term)));
         }
@@ -310,26 +538,73 @@ pub async fn run_transcribe_mode(
 
     // Add keywords parameters if specified (each entry becomes a separate keywords= param,
     // optionally with an intensifier: "word:2.0" or just "word")
-    if let Some(kw) = args.keywords {
+    if let Some(kw) = &options.keywords {
         for entry in kw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
             params.push(format!("keywords={}", urlencoding::encode(entry)));
         }
     }
 
-    // Join all parameters
-    url.push_str(&params.join("&"));
+    // Add utterances and paragraphs parameters
+    if options.utterances {
+        params.push("utterances=true".to_string());
+    }
+    if options.paragraphs {
+        params.push("paragraphs=true".to_string());
+    }
 
-    println!("Sending request to Deepgram API...");
+    // Add search and replace parameters (one parameter per term)
+    for term in &options.search {
+        params.push(format!("search={}", urlencoding::encode(term)));
+    }
+    for replacement in &options.replace {
+        params.push(format!("replace={}", urlencoding::encode(replacement)));
+    }
 
-    // Create HTTP client
-    let client = reqwest::Client::new();
+    push_extra_params(&mut params, &options.params);
+
+    if let Some(callback) = callback {
+        params.push(format!("callback={}", urlencoding::encode(callback)));
+    }
+
+    // Join all parameters
+    url.push_str(&params.join("&"));
+    url
+}
 
-    // Send POST request
-    let mut request = client
-        .post(&url)
+/// POST audio bytes to `/v1/listen` and return the raw JSON response body.
+pub(crate) async fn post_audio(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<&str>,
+    audio_data: Vec<u8>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let request = client
+        .post(url)
         .header("Content-Type", "application/octet-stream")
         .body(audio_data);
 
+    send_listen_request(request, api_key).await
+}
+
+/// Ask `/v1/listen` to fetch remote audio from `source_url` and return the raw
+/// JSON response body.
+pub(crate) async fn post_url(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<&str>,
+    source_url: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let request = client
+        .post(url)
+        .json(&serde_json::json!({ "url": source_url }));
+
+    send_listen_request(request, api_key).await
+}
+
+async fn send_listen_request(
+    mut request: reqwest::RequestBuilder,
+    api_key: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
     if let Some(api_key) = api_key {
         request = request.header("Authorization", format!("Token {}", api_key));
     }
@@ -343,136 +618,567 @@ pub async fn run_transcribe_mode(
         return Err(format!("API request failed with status {}: {}", status, error_text).into());
     }
 
-    // Parse response
-    let response_text = response.text().await?;
+    Ok(response.text().await?)
+}
+
+/// Request ID and audio duration from a pre-recorded response, if it parses.
+pub(crate) fn response_summary(response_text: &str) -> Option<(String, f64)> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        metadata: PreRecordedMetadata,
+    }
+
+    serde_json::from_str::<Envelope>(response_text)
+        .ok()
+        .map(|envelope| (envelope.metadata.request_id, envelope.metadata.duration))
+}
+
+/// Transcript of the first alternative of every channel, joined with spaces.
+pub(crate) fn response_transcript(
+    response_text: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let response: PreRecordedResponse = serde_json::from_str(response_text)?;
+    let transcripts: Vec<&str> = response
+        .results
+        .channels
+        .iter()
+        .filter_map(|channel| channel.alternatives.first())
+        .map(|alternative| alternative.transcript.as_str())
+        .filter(|transcript| !transcript.is_empty())
+        .collect();
+    Ok(transcripts.join(" "))
+}
+
+/// Request ID from the immediate acknowledgement of a callback request.
+fn callback_request_id(response_text: &str) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Acknowledgement {
+        request_id: String,
+    }
+
+    serde_json::from_str::<Acknowledgement>(response_text)
+        .ok()
+        .map(|ack| ack.request_id)
+}
+
+/// File extension used when a rendered `--output` format is written to disk.
+pub(crate) fn output_extension(output: &str) -> &'static str {
+    match output {
+        "json" | "verbose-json" => "json",
+        "srt" => "srt",
+        "vtt" => "vtt",
+        _ => "txt",
+    }
+}
 
-    // Output based on format
-    match args.output.as_str() {
+/// Render a pre-recorded response in the requested `--output` format.
+pub(crate) fn render_output(
+    response_text: &str,
+    options: &TranscribeOptions,
+) -> Result<String, Box<dyn std::error::Error>> {
+    match options.output.as_str() {
+        // Parse and pretty-print JSON
         "json" => {
-            // Parse and pretty-print JSON
-            let parsed: serde_json::Value = serde_json::from_str(&response_text)?;
-            println!("\n{}", serde_json::to_string_pretty(&parsed)?);
+            let parsed: serde_json::Value = serde_json::from_str(response_text)?;
+            Ok(format!("{}\n", serde_json::to_string_pretty(&parsed)?))
         }
-        "verbose-json" => {
-            // Output raw JSON response
-            println!("\n{}", response_text);
+        // Output raw JSON response
+        "verbose-json" => Ok(format!("{}\n", response_text)),
+        "srt" | "vtt" => {
+            let response: PreRecordedResponse = serde_json::from_str(response_text)?;
+            let format = SubtitleFormat::from_name(&options.output).unwrap_or(SubtitleFormat::Srt);
+            Ok(render_subtitles(
+                &response.results,
+                &options.subtitles,
+                format,
+            ))
         }
-        "text" | _ => {
-            // Parse and display transcript with additional info
-            let response: PreRecordedResponse = serde_json::from_str(&response_text)?;
-
-            println!("\n=== Transcription Results ===");
-            println!("Request ID: {}", response.metadata.request_id);
-            println!("Duration: {:.2}s", response.metadata.duration);
-            println!("Channels: {}", response.metadata.channels);
-            println!();
-
-            for (i, channel) in response.results.channels.iter().enumerate() {
-                if response.results.channels.len() > 1 {
-                    println!("Channel {}:", i);
-                }
+        // Parse and display transcript with additional info
+        _ => {
+            let response: PreRecordedResponse = serde_json::from_str(response_text)?;
+            Ok(render_text(&response)?)
+        }
+    }
+}
 
-                for alternative in &channel.alternatives {
-                    println!("Transcript:");
-                    println!("{}", alternative.transcript);
-                    println!("\nConfidence: {:.1}%", alternative.confidence * 100.0);
-
-                    // Display speaker diarization if available
-                    if alternative.words.iter().any(|w| w.speaker.is_some()) {
-                        println!("\n=== Speaker Diarization ===");
-                        let mut current_speaker: Option<u32> = None;
-                        let mut speaker_text = String::new();
-
-                        for word in &alternative.words {
-                            if let Some(speaker) = word.speaker {
-                                if current_speaker != Some(speaker) {
-                                    if !speaker_text.is_empty() {
-                                        println!(
-                                            "Speaker {}: {}",
-                                            current_speaker.unwrap(),
-                                            speaker_text.trim()
-                                        );
-                                        speaker_text.clear();
-                                    }
-                                    current_speaker = Some(speaker);
-                                }
-                                speaker_text.push_str(&format!(
-                                    "{} ",
-                                    word.punctuated_word.as_ref().unwrap_or(&word.word)
-                                ));
-                            }
-                        }
-                        if !speaker_text.is_empty() {
-                            println!(
-                                "Speaker {}: {}",
-                                current_speaker.unwrap(),
-                                speaker_text.trim()
-                            );
-                        }
-                    }
+fn render_text(response: &PreRecordedResponse) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
 
-                    // Display entities if available
-                    if !alternative.entities.is_empty() {
-                        println!("\n=== Detected Entities ===");
-                        for entity in &alternative.entities {
-                            println!(
-                                "{}: {} (confidence: {:.1}%)",
-                                entity.label,
-                                entity.value,
-                                entity.confidence * 100.0
-                            );
-                        }
-                    }
+    writeln!(out, "=== Transcription Results ===")?;
+    writeln!(out, "Request ID: {}", response.metadata.request_id)?;
+    writeln!(out, "Duration: {:.2}s", response.metadata.duration)?;
+    writeln!(out, "Channels: {}", response.metadata.channels)?;
+    writeln!(out)?;
 
-                    // Display topics if available
-                    if !alternative.topics.is_empty() {
-                        println!("\n=== Topics ===");
-                        for topic in &alternative.topics {
-                            println!(
-                                "{} (confidence: {:.1}%)",
-                                topic.topic,
-                                topic.confidence * 100.0
-                            );
-                        }
-                    }
+    for (i, channel) in response.results.channels.iter().enumerate() {
+        if response.results.channels.len() > 1 {
+            writeln!(out, "Channel {}:", i)?;
+        }
+        if let Some(language) = &channel.detected_language {
+            write!(out, "Detected Language: {language}")?;
+            if let Some(confidence) = channel.language_confidence {
+                write!(out, " (confidence: {:.1}%)", confidence * 100.0)?;
+            }
+            writeln!(out)?;
+        }
 
-                    // Display intents if available
-                    if !alternative.intents.is_empty() {
-                        println!("\n=== Intents ===");
-                        for intent in &alternative.intents {
-                            println!(
-                                "{} (confidence: {:.1}%)",
-                                intent.intent,
-                                intent.confidence * 100.0
-                            );
-                        }
-                    }
+        // Utterances span the whole response; each belongs to one channel
+        let utterances: Vec<&PreRecordedUtterance> = response
+            .results
+            .utterances
+            .iter()
+            .filter(|utterance| utterance.channel == i)
+            .collect();
+
+        for (alt_index, alternative) in channel.alternatives.iter().enumerate() {
+            writeln!(out, "Transcript:")?;
+            let paragraphs = alternative
+                .paragraphs
+                .as_ref()
+                .map(|p| p.paragraphs.as_slice())
+                .unwrap_or_default();
+            // Utterances are only returned for the top alternative
+            let segmented = if alt_index == 0 && !utterances.is_empty() {
+                write_utterances(&mut out, &utterances)?;
+                true
+            } else if !paragraphs.is_empty() {
+                write_paragraphs(&mut out, paragraphs)?;
+                true
+            } else {
+                writeln!(out, "{}", alternative.transcript)?;
+                false
+            };
+            writeln!(out, "\nConfidence: {:.1}%", alternative.confidence * 100.0)?;
+            if !alternative.languages.is_empty() {
+                writeln!(out, "Languages: {}", alternative.languages.join(", "))?;
+            }
 
-                    // Display summaries if available
-                    if !alternative.summaries.is_empty() {
-                        println!("\n=== Summaries ===");
-                        for summary in &alternative.summaries {
-                            if !summary.summary.is_empty() {
-                                println!("{}", summary.summary);
+            // Display speaker diarization if available and not already shown
+            // alongside the utterances or paragraphs
+            if !segmented && alternative.words.iter().any(|w| w.speaker.is_some()) {
+                writeln!(out, "\n=== Speaker Diarization ===")?;
+                let mut current_speaker: Option<u32> = None;
+                let mut speaker_text = String::new();
+
+                for word in &alternative.words {
+                    if let Some(speaker) = word.speaker {
+                        if current_speaker != Some(speaker) {
+                            if !speaker_text.is_empty() {
+                                writeln!(
+                                    out,
+                                    "Speaker {}: {}",
+                                    current_speaker.unwrap(),
+                                    speaker_text.trim()
+                                )?;
+                                speaker_text.clear();
                             }
+                            current_speaker = Some(speaker);
                         }
+                        speaker_text.push_str(&format!(
+                            "{} ",
+                            word.punctuated_word.as_ref().unwrap_or(&word.word)
+                        ));
                     }
+                }
+                if !speaker_text.is_empty() {
+                    writeln!(
+                        out,
+                        "Speaker {}: {}",
+                        current_speaker.unwrap(),
+                        speaker_text.trim()
+                    )?;
+                }
+            }
 
-                    // Display sentiment analysis if available
-                    if !alternative.sentiments.is_empty() {
-                        println!("\n=== Sentiment Analysis ===");
-                        for sentiment in &alternative.sentiments {
-                            println!(
-                                "{} (confidence: {:.1}%)",
-                                sentiment.sentiment,
-                                sentiment.confidence * 100.0
-                            );
-                        }
+            // Display which language each stretch was in when the speakers
+            // switch languages
+            let segments = language_segments(&alternative.words);
+            if segments.len() > 1 {
+                writeln!(out, "\n=== Language Segments ===")?;
+                for segment in &segments {
+                    writeln!(
+                        out,
+                        "[{} - {}] {}: {}",
+                        format_offset(segment.start),
+                        format_offset(segment.end),
+                        segment.language,
+                        segment.words.join(" ")
+                    )?;
+                }
+            }
+
+            // Display entities if available
+            if !alternative.entities.is_empty() {
+                writeln!(out, "\n=== Detected Entities ===")?;
+                for entity in &alternative.entities {
+                    writeln!(
+                        out,
+                        "{}: {} (confidence: {:.1}%)",
+                        entity.label,
+                        entity.value,
+                        entity.confidence * 100.0
+                    )?;
+                }
+            }
+
+            // Display topics if available
+            if !alternative.topics.is_empty() {
+                writeln!(out, "\n=== Topics ===")?;
+                for topic in &alternative.topics {
+                    writeln!(
+                        out,
+                        "{} (confidence: {:.1}%)",
+                        topic.topic,
+                        topic.confidence * 100.0
+                    )?;
+                }
+            }
+
+            // Display intents if available
+            if !alternative.intents.is_empty() {
+                writeln!(out, "\n=== Intents ===")?;
+                for intent in &alternative.intents {
+                    writeln!(
+                        out,
+                        "{} (confidence: {:.1}%)",
+                        intent.intent,
+                        intent.confidence * 100.0
+                    )?;
+                }
+            }
+
+            // Display summaries if available
+            if !alternative.summaries.is_empty() {
+                writeln!(out, "\n=== Summaries ===")?;
+                for summary in &alternative.summaries {
+                    if !summary.summary.is_empty() {
+                        writeln!(out, "{}", summary.summary)?;
                     }
                 }
             }
+
+            // Display sentiment analysis if available
+            if !alternative.sentiments.is_empty() {
+                writeln!(out, "\n=== Sentiment Analysis ===")?;
+                for sentiment in &alternative.sentiments {
+                    writeln!(
+                        out,
+                        "{} (confidence: {:.1}%)",
+                        sentiment.sentiment,
+                        sentiment.confidence * 100.0
+                    )?;
+                }
+            }
+        }
+
+        // Display search hits if available
+        if !channel.search.is_empty() {
+            writeln!(out, "\n=== Search Hits ===")?;
+            for search in &channel.search {
+                writeln!(out, "\"{}\": {} hit(s)", search.query, search.hits.len())?;
+                for hit in &search.hits {
+                    writeln!(
+                        out,
+                        "  [{} - {}] {} (confidence: {:.1}%)",
+                        format_offset(hit.start),
+                        format_offset(hit.end),
+                        hit.snippet,
+                        hit.confidence * 100.0
+                    )?;
+                }
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// `[start - end] Speaker N: ` prefix for a timestamped segment.
+fn segment_heading(start: f64, end: f64, speaker: Option<u32>) -> String {
+    let mut heading = format!("[{} - {}]", format_offset(start), format_offset(end));
+    if let Some(speaker) = speaker {
+        heading.push_str(&format!(" Speaker {speaker}:"));
+    }
+    heading
+}
+
+fn write_utterances(
+    out: &mut String,
+    utterances: &[&PreRecordedUtterance],
+) -> Result<(), std::fmt::Error> {
+    for utterance in utterances {
+        writeln!(
+            out,
+            "{} {}",
+            segment_heading(utterance.start, utterance.end, utterance.speaker),
+            utterance.transcript
+        )?;
+    }
+    Ok(())
+}
+
+/// One block per paragraph: a timestamp heading, then its sentences, with a
+/// blank line between paragraphs.
+fn write_paragraphs(
+    out: &mut String,
+    paragraphs: &[PreRecordedParagraph],
+) -> Result<(), std::fmt::Error> {
+    for (index, paragraph) in paragraphs.iter().enumerate() {
+        let (Some(first), Some(last)) = (paragraph.sentences.first(), paragraph.sentences.last())
+        else {
+            continue;
+        };
+        if index > 0 {
+            writeln!(out)?;
+        }
+        let text: Vec<&str> = paragraph
+            .sentences
+            .iter()
+            .map(|sentence| sentence.text.as_str())
+            .collect();
+        writeln!(
+            out,
+            "{}",
+            segment_heading(first.start, last.end, paragraph.speaker)
+        )?;
+        writeln!(out, "{}", text.join(" "))?;
+    }
+    Ok(())
+}
+
+pub async fn run_transcribe_mode(
+    api_key: Option<String>,
+    args: TranscribeArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Bind the local receiver before sending the request so the callback cannot
+    // arrive before anything is listening. A dry run only shows the address.
+    let receiver = match &args.listen_callback {
+        Some(addr) if !args.dry_run => Some(CallbackReceiver::bind(addr).await?),
+        _ => None,
+    };
+    let callback_url = match (&args.callback, &receiver, &args.listen_callback) {
+        (Some(callback_url), _, _) => Some(callback_url.clone()),
+        (None, Some(receiver), _) => Some(format!("http://{}/callback", receiver.local_addr()?)),
+        (None, None, Some(addr)) => Some(format!("http://{addr}/callback")),
+        (None, None, None) => None,
+    };
+
+    let url = build_listen_url(&args.options, callback_url.as_deref());
+    if args.dry_run {
+        let (content_type, body) = match (&args.file, &args.url) {
+            (_, Some(source_url)) => (
+                "application/json",
+                serde_json::json!({ "url": source_url }).to_string(),
+            ),
+            (Some(file), None) => (
+                "application/octet-stream",
+                format!(
+                    "({} bytes from {})",
+                    std::fs::metadata(file)?.len(),
+                    file.display()
+                ),
+            ),
+            (None, None) => return Err("Either --file or --url is required".into()),
+        };
+        let mut headers = vec![("Content-Type", content_type.to_string())];
+        if let Some(api_key) = &api_key {
+            headers.push(("Authorization", redacted_authorization(api_key)));
+        }
+        print_dry_run("POST", &url, &headers);
+        println!("\n{body}");
+        return Ok(());
+    }
+
+    let client = reqwest::Client::new();
+
+    let response_text = match (&args.file, &args.url) {
+        (_, Some(source_url)) => {
+            url::Url::parse(source_url).map_err(|e| format!("Invalid --url {source_url}: {e}"))?;
+            eprintln!("Transcribing audio URL: {}", source_url);
+            eprintln!("Sending request to Deepgram API...");
+            post_url(&client, &url, api_key.as_deref(), source_url).await?
+        }
+        (Some(file), None) => {
+            eprintln!("Transcribing audio file: {}", file.display());
+
+            // Read the audio file
+            let audio_data = std::fs::read(file)?;
+            eprintln!("Read {} bytes from file", audio_data.len());
+
+            eprintln!("Sending request to Deepgram API...");
+            post_audio(&client, &url, api_key.as_deref(), audio_data).await?
         }
+        (None, None) => return Err("Either --file or --url is required".into()),
+    };
+
+    if let Some(callback_url) = callback_url {
+        let request_id = callback_request_id(&response_text)
+            .ok_or("Callback request was accepted without a request ID")?;
+        let Some(receiver) = receiver else {
+            println!("Request ID: {}", request_id);
+            println!("Deepgram will POST the result to {}", callback_url);
+            return Ok(());
+        };
+
+        eprintln!("Request ID: {}", request_id);
+        eprintln!(
+            "Waiting for callback on {} (callback URL: {})...",
+            receiver.local_addr()?,
+            callback_url
+        );
+        let callback_body = receiver
+            .wait_for(&request_id, Duration::from_secs(args.callback_timeout))
+            .await?;
+        print!("{}", render_output(&callback_body, &args.options)?);
+        return Ok(());
     }
 
+    print!("{}", render_output(&response_text, &args.options)?);
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        PreRecordedResponse, detect_language_params, format_offset, parse_replacement, render_text,
+    };
+
+    fn transcript_section(text: &str) -> &str {
+        let start = text.find("Transcript:\n").unwrap() + "Transcript:\n".len();
+        &text[start..text.find("\nConfidence:").unwrap()]
+    }
+
+    #[test]
+    fn renders_utterances_or_paragraphs_instead_of_one_blob() {
+        let json = r#"{
+            "metadata": {"request_id": "req-2", "duration": 20.0, "channels": 1},
+            "results": {"channels": [{"alternatives": [{
+                "transcript": "Hi there. How can I help? I need a refund.",
+                "confidence": 0.95,
+                "words": [{"word": "hi", "start": 0.5, "end": 0.8, "confidence": 0.9, "speaker": 0}],
+                "paragraphs": {"paragraphs": [
+                    {"speaker": 0, "sentences": [
+                        {"text": "Hi there.", "start": 0.5, "end": 1.2},
+                        {"text": "How can I help?", "start": 1.4, "end": 2.9}
+                    ]},
+                    {"speaker": 1, "sentences": [{"text": "I need a refund.", "start": 3.5, "end": 5.0}]}
+                ]}
+            }]}]UTTERANCES}
+        }"#;
+
+        let response: PreRecordedResponse =
+            serde_json::from_str(&json.replace("UTTERANCES", "")).unwrap();
+        let text = render_text(&response).unwrap();
+        assert_eq!(
+            transcript_section(&text),
+            "[00:00.5 - 00:02.9] Speaker 0:\nHi there. How can I help?\n\n\
+             [00:03.5 - 00:05.0] Speaker 1:\nI need a refund.\n"
+        );
+        assert!(!text.contains("Speaker Diarization"));
+
+        let response: PreRecordedResponse = serde_json::from_str(&json.replace(
+            "UTTERANCES",
+            r#", "utterances": [
+                {"start": 0.5, "end": 2.9, "transcript": "Hi there. How can I help?", "channel": 0, "speaker": 0},
+                {"start": 3.5, "end": 5.0, "transcript": "I need a refund.", "channel": 0, "speaker": 1}
+            ]"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            transcript_section(&render_text(&response).unwrap()),
+            "[00:00.5 - 00:02.9] Speaker 0: Hi there. How can I help?\n\
+             [00:03.5 - 00:05.0] Speaker 1: I need a refund.\n"
+        );
+    }
+
+    #[test]
+    fn renders_search_hits_with_timestamps() {
+        let response: PreRecordedResponse = serde_json::from_str(
+            r#"{
+                "metadata": {"request_id": "req-1", "duration": 3725.0, "channels": 1},
+                "results": {"channels": [{
+                    "alternatives": [{"transcript": "I want to cancel my account", "confidence": 0.9}],
+                    "search": [
+                        {"query": "cancel my account", "hits": [
+                            {"confidence": 0.92, "start": 61.2, "end": 62.4, "snippet": "cancel my account"},
+                            {"confidence": 0.71, "start": 3700.0, "end": 3701.5, "snippet": "cancel the account"}
+                        ]},
+                        {"query": "refund", "hits": []}
+                    ]
+                }]}
+            }"#,
+        )
+        .unwrap();
+
+        let text = render_text(&response).unwrap();
+        let hits = &text[text.find("=== Search Hits ===").unwrap()..];
+        assert_eq!(
+            hits,
+            "=== Search Hits ===\n\
+             \"cancel my account\": 2 hit(s)\n  \
+             [01:01.2 - 01:02.4] cancel my account (confidence: 92.0%)\n  \
+             [1:01:40.0 - 1:01:41.5] cancel the account (confidence: 71.0%)\n\
+             \"refund\": 0 hit(s)\n"
+        );
+
+        assert_eq!(format_offset(59.96), "01:00.0");
+        assert_eq!(
+            parse_replacement("acme:Acme:Co"),
+            Ok("acme:Acme:Co".to_string())
+        );
+        assert_eq!(parse_replacement("um:"), Ok("um:".to_string()));
+        assert!(parse_replacement("acme").is_err());
+        assert!(parse_replacement(":Acme").is_err());
+    }
+
+    #[test]
+    fn renders_detected_language_and_language_segments() {
+        let response: PreRecordedResponse = serde_json::from_str(
+            r#"{
+                "metadata": {"request_id": "req-3", "duration": 4.0, "channels": 1},
+                "results": {"channels": [{
+                    "detected_language": "es",
+                    "language_confidence": 0.874,
+                    "alternatives": [{
+                        "transcript": "hola buenos días how can I help",
+                        "confidence": 0.9,
+                        "languages": ["es", "en"],
+                        "words": [
+                            {"word": "hola", "start": 0.2, "end": 0.5, "confidence": 0.9},
+                            {"word": "buenos", "start": 0.6, "end": 0.9, "confidence": 0.9, "language": "es"},
+                            {"word": "días", "start": 0.9, "end": 1.3, "confidence": 0.9},
+                            {"word": "how", "start": 1.8, "end": 2.0, "confidence": 0.9, "language": "en"},
+                            {"word": "can", "start": 2.0, "end": 2.2, "confidence": 0.9, "language": "en"},
+                            {"word": "I", "start": 2.2, "end": 2.3, "confidence": 0.9, "language": "en"},
+                            {"word": "help", "start": 2.3, "end": 2.7, "confidence": 0.9, "language": "en",
+                             "punctuated_word": "help?"}
+                        ]
+                    }]
+                }]}
+            }"#,
+        )
+        .unwrap();
+
+        let text = render_text(&response).unwrap();
+        assert!(text.contains("Channels: 1\n\nDetected Language: es (confidence: 87.4%)\n"));
+        assert!(text.contains("Confidence: 90.0%\nLanguages: es, en\n"));
+        let segments = &text[text.find("=== Language Segments ===").unwrap()..];
+        assert_eq!(
+            segments,
+            "=== Language Segments ===\n\
+             [00:00.2 - 00:01.3] es: hola buenos días\n\
+             [00:01.8 - 00:02.7] en: how can I help?\n"
+        );
+
+        assert_eq!(detect_language_params(None), ["detect_language=true"]);
+        assert_eq!(
+            detect_language_params(Some("true")),
+            ["detect_language=true"]
+        );
+        assert_eq!(
+            detect_language_params(Some("en, es")),
+            ["detect_language=en", "detect_language=es"]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::protocol::Word;
+
+/// One line of the transcript log, written for each finalized `Results` message.
+#[derive(Debug, Serialize)]
+pub(crate) struct TranscriptLogEntry<'a> {
+    pub(crate) connection: usize,
+    /// Channel label with --split-channels.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) channel_label: Option<&'a str>,
+    pub(crate) request_id: Option<&'a str>,
+    pub(crate) start: f64,
+    pub(crate) duration: f64,
+    pub(crate) is_final: bool,
+    pub(crate) speech_final: bool,
+    pub(crate) from_finalize: bool,
+    #[serde(skip_serializing_if = "<[u32]>::is_empty")]
+    pub(crate) channel_index: &'a [u32],
+    pub(crate) transcript: &'a str,
+    pub(crate) confidence: Option<f64>,
+    pub(crate) words: &'a [Word],
+}
+
+/// Appends transcript log entries as JSON lines. Every connection opens the
+/// same file in append mode and writes each line with a single `write_all`, so
+/// lines from parallel connections interleave without being split.
+pub(crate) struct TranscriptLog {
+    file: File,
+}
+
+impl TranscriptLog {
+    pub(crate) fn open(path: &Path) -> std::io::Result<Self> {
+        Ok(TranscriptLog {
+            file: OpenOptions::new().create(true).append(true).open(path)?,
+        })
+    }
+
+    pub(crate) fn write(&mut self, entry: &TranscriptLogEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TranscriptLog, TranscriptLogEntry};
+    use crate::protocol::Word;
+
+    #[test]
+    fn writes_one_json_line_per_entry() {
+        let path = std::env::temp_dir().join(format!(
+            "dg-stt-transcript-log-test-{}.jsonl",
+            std::process::id()
+        ));
+        let words: Vec<Word> = serde_json::from_str(
+            r#"[{"word":"hello","start":0.5,"end":0.9,"confidence":0.98,"speaker":1,"punctuated_word":"Hello."}]"#,
+        )
+        .unwrap();
+
+        for connection in [1, 2] {
+            let mut log = TranscriptLog::open(&path).unwrap();
+            log.write(&TranscriptLogEntry {
+                connection,
+                channel_label: None,
+                request_id: Some("abc"),
+                start: 0.0,
+                duration: 1.2,
+                is_final: true,
+                speech_final: connection == 2,
+                from_finalize: false,
+                channel_index: &[],
+                transcript: "Hello.",
+                confidence: Some(0.98),
+                words: &words,
+            })
+            .unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["connection"], 1);
+        assert_eq!(lines[1]["speech_final"], true);
+        assert_eq!(lines[0]["request_id"], "abc");
+        assert_eq!(lines[0]["words"][0]["speaker"], 1);
+        assert_eq!(lines[0]["words"][0]["confidence"], 0.98);
+        assert_eq!(lines[0]["words"][0]["punctuated_word"], "Hello.");
+    }
+}
@@ -0,0 +1,343 @@
+use serde::Serialize;
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+const SCALES: [(u64, &str); 4] = [
+    (1_000_000_000_000, "trillion"),
+    (1_000_000_000, "billion"),
+    (1_000_000, "million"),
+    (1_000, "thousand"),
+];
+
+/// Substitution, insertion, and deletion counts from aligning a hypothesis
+/// against a reference of `reference_len` tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct ErrorCounts {
+    pub(crate) reference_len: usize,
+    pub(crate) substitutions: usize,
+    pub(crate) insertions: usize,
+    pub(crate) deletions: usize,
+}
+
+impl ErrorCounts {
+    pub(crate) fn errors(&self) -> usize {
+        self.substitutions + self.insertions + self.deletions
+    }
+
+    /// Error rate as a fraction. An empty reference scores 0 if the hypothesis
+    /// is also empty and 1 otherwise.
+    pub(crate) fn rate(&self) -> f64 {
+        match self.reference_len {
+            0 if self.errors() == 0 => 0.0,
+            0 => 1.0,
+            len => self.errors() as f64 / len as f64,
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: &ErrorCounts) {
+        self.reference_len += other.reference_len;
+        self.substitutions += other.substitutions;
+        self.insertions += other.insertions;
+        self.deletions += other.deletions;
+    }
+}
+
+/// Minimum edit alignment of `hypothesis` against `reference`. Ties prefer
+/// substitutions, then deletions, then insertions, so counts are stable.
+///
+/// Only two rows of the table are kept, each cell carrying its own counts, so
+/// memory grows with the hypothesis rather than with both lengths multiplied;
+/// character alignment of a long recording stays small.
+pub(crate) fn align<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> ErrorCounts {
+    let cell = |substitutions, insertions, deletions| ErrorCounts {
+        reference_len: 0,
+        substitutions,
+        insertions,
+        deletions,
+    };
+    let mut previous: Vec<ErrorCounts> = (0..=hypothesis.len()).map(|j| cell(0, j, 0)).collect();
+    let mut current = previous.clone();
+    for (i, expected) in reference.iter().enumerate() {
+        current[0] = cell(0, 0, i + 1);
+        for (j, actual) in hypothesis.iter().enumerate() {
+            let mut diagonal = previous[j];
+            if expected != actual {
+                diagonal.substitutions += 1;
+            }
+            let mut deletion = previous[j + 1];
+            deletion.deletions += 1;
+            let mut insertion = current[j];
+            insertion.insertions += 1;
+            current[j + 1] = [diagonal, deletion, insertion]
+                .into_iter()
+                .min_by_key(ErrorCounts::errors)
+                .unwrap_or_default();
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    ErrorCounts {
+        reference_len: reference.len(),
+        ..previous[hypothesis.len()]
+    }
+}
+
+/// Word and character error counts for one reference/hypothesis pair. Both
+/// texts should already be normalized; characters are compared over the words
+/// joined with single spaces.
+pub(crate) fn score(reference: &[String], hypothesis: &[String]) -> (ErrorCounts, ErrorCounts) {
+    let words = align(reference, hypothesis);
+    let reference_chars: Vec<char> = reference.join(" ").chars().collect();
+    let hypothesis_chars: Vec<char> = hypothesis.join(" ").chars().collect();
+    (words, align(&reference_chars, &hypothesis_chars))
+}
+
+/// Split `text` into words without normalizing anything beyond whitespace.
+pub(crate) fn split_words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_string).collect()
+}
+
+/// Normalize `text` for scoring: lowercase it, drop punctuation (keeping
+/// apostrophes inside words), split hyphenated words, and spell out numerals
+/// so "42%" and "forty-two percent" compare equal.
+pub(crate) fn normalize(text: &str) -> Vec<String> {
+    let lowered = text.to_lowercase();
+    let spaced: String = lowered
+        .chars()
+        .map(|c| match c {
+            '-' | '–' | '—' | '/' | '_' => ' ',
+            c => c,
+        })
+        .collect();
+
+    let mut words = Vec::new();
+    for token in spaced.split_whitespace() {
+        let percent = token.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '%');
+        let (token, percent) = match percent.strip_suffix('%') {
+            Some(stripped) => (stripped, true),
+            None => (token, false),
+        };
+        let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+        if token.is_empty() {
+            continue;
+        }
+
+        match numeral_words(token) {
+            Some(spoken) => words.extend(spoken.split(' ').map(str::to_string)),
+            None => {
+                let word: String = token
+                    .chars()
+                    .filter(|c| c.is_alphanumeric() || *c == '\'' || *c == '’')
+                    .map(|c| if c == '’' { '\'' } else { c })
+                    .collect();
+                if !word.is_empty() {
+                    words.push(word);
+                }
+            }
+        }
+        if percent {
+            words.push("percent".to_string());
+        }
+    }
+    words
+}
+
+/// Spell out a cardinal ("1,200"), decimal ("3.5"), or ordinal ("21st") numeral.
+fn numeral_words(token: &str) -> Option<String> {
+    let digits_only = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    for suffix in ["st", "nd", "rd", "th"] {
+        if let Some(number) = token.strip_suffix(suffix)
+            && digits_only(number)
+        {
+            return Some(ordinal(&cardinal(number)));
+        }
+    }
+
+    let (whole, fraction) = match token.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (token, None),
+    };
+    let whole = whole.replace(',', "");
+    if !digits_only(&whole) || fraction.is_some_and(|f| !digits_only(f)) {
+        return None;
+    }
+
+    let mut words = cardinal(&whole);
+    if let Some(fraction) = fraction {
+        words.push_str(" point");
+        for digit in fraction.bytes() {
+            words.push(' ');
+            words.push_str(ONES[(digit - b'0') as usize]);
+        }
+    }
+    Some(words)
+}
+
+/// Spell out a string of digits. Numbers too large for `u64`, or with a
+/// leading zero (such as "007"), are read digit by digit.
+fn cardinal(digits: &str) -> String {
+    match digits.parse::<u64>() {
+        Ok(n) if !(digits.len() > 1 && digits.starts_with('0')) => cardinal_words(n),
+        _ => {
+            let spoken: Vec<&str> = digits
+                .bytes()
+                .map(|digit| ONES[(digit - b'0') as usize])
+                .collect();
+            spoken.join(" ")
+        }
+    }
+}
+
+fn cardinal_words(mut n: u64) -> String {
+    if n < 1000 {
+        return below_thousand(n);
+    }
+    let mut parts = Vec::new();
+    for (scale, name) in SCALES {
+        if n >= scale {
+            parts.push(format!("{} {}", cardinal_words(n / scale), name));
+            n %= scale;
+        }
+    }
+    if n > 0 {
+        parts.push(below_thousand(n));
+    }
+    parts.join(" ")
+}
+
+fn below_thousand(n: u64) -> String {
+    let n = n as usize;
+    match n {
+        0..20 => ONES[n].to_string(),
+        20..100 if n.is_multiple_of(10) => TENS[n / 10].to_string(),
+        20..100 => format!("{} {}", TENS[n / 10], ONES[n % 10]),
+        _ if n.is_multiple_of(100) => format!("{} hundred", ONES[n / 100]),
+        _ => format!(
+            "{} hundred {}",
+            ONES[n / 100],
+            below_thousand((n % 100) as u64)
+        ),
+    }
+}
+
+fn ordinal(cardinal: &str) -> String {
+    let (head, last) = match cardinal.rsplit_once(' ') {
+        Some((head, last)) => (Some(head), last),
+        None => (None, cardinal),
+    };
+    let last = match last {
+        "one" => "first".to_string(),
+        "two" => "second".to_string(),
+        "three" => "third".to_string(),
+        "five" => "fifth".to_string(),
+        "eight" => "eighth".to_string(),
+        "nine" => "ninth".to_string(),
+        "twelve" => "twelfth".to_string(),
+        word if word.ends_with('y') => format!("{}ieth", &word[..word.len() - 1]),
+        word => format!("{word}th"),
+    };
+    match head {
+        Some(head) => format!("{head} {last}"),
+        None => last,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorCounts, align, normalize, score};
+
+    #[test]
+    fn normalizes_case_punctuation_and_numerals() {
+        assert_eq!(
+            normalize("Hello, World! It's 42% of the 1,250 calls on the 21st."),
+            vec![
+                "hello", "world", "it's", "forty", "two", "percent", "of", "the", "one",
+                "thousand", "two", "hundred", "fifty", "calls", "on", "the", "twenty", "first"
+            ]
+        );
+        assert_eq!(
+            normalize("Forty-two — version 3.05, agent 007"),
+            vec![
+                "forty", "two", "version", "three", "point", "zero", "five", "agent", "zero",
+                "zero", "seven"
+            ]
+        );
+        assert_eq!(normalize("Don’t stop"), vec!["don't", "stop"]);
+    }
+
+    #[test]
+    fn counts_substitutions_insertions_and_deletions() {
+        let words = |s: &str| normalize(s);
+        let counts = align(
+            &words("the cat sat on the mat"),
+            &words("so the bat sat on mat"),
+        );
+        assert_eq!(
+            counts,
+            ErrorCounts {
+                reference_len: 6,
+                substitutions: 1,
+                insertions: 1,
+                deletions: 1,
+            }
+        );
+        assert!((counts.rate() - 0.5).abs() < 1e-9);
+
+        let (wer, cer) = score(&words("hello world"), &words("hello word"));
+        assert_eq!((wer.substitutions, wer.reference_len), (1, 2));
+        assert_eq!((cer.errors(), cer.reference_len), (1, 11));
+
+        assert_eq!(align::<String>(&[], &[]).rate(), 0.0);
+        assert_eq!(align(&[], &words("extra")).rate(), 1.0);
+    }
+
+    #[test]
+    fn aligns_long_character_sequences() {
+        // A couple of thousand characters, with edits far apart so every one
+        // has to be carried across many rows.
+        let reference: Vec<char> = "the quick brown fox jumps over the lazy dog "
+            .repeat(50)
+            .chars()
+            .collect();
+        let mut hypothesis = reference.clone();
+        hypothesis[100] = 'x';
+        hypothesis.remove(900);
+        hypothesis.insert(2_000, 'y');
+
+        assert_eq!(
+            align(&reference, &hypothesis),
+            ErrorCounts {
+                reference_len: reference.len(),
+                substitutions: 1,
+                insertions: 1,
+                deletions: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Runs the `dg-stt` binary against the scripted mock in `mock_deepgram`.
+
+mod mock_deepgram;
+
+use mock_deepgram::{BAD_API_KEY, MockDeepgram, STREAM_SCRIPT};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Duration;
+
+struct Output {
+    status: ExitStatus,
+    stdout: String,
+    stderr: String,
+}
+
+/// A scratch directory per test, so `.env` files and debug logs in the
+/// source tree stay out of the way.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dg-stt-e2e-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `seconds` of a 440 Hz tone as a 16 kHz mono 16-bit WAV file and
+/// return the size of its data chunk.
+fn write_wav(path: &Path, seconds: f64) -> usize {
+    let sample_rate = 16000u32;
+    let samples = (seconds * sample_rate as f64) as usize;
+    let data_len = samples * 2;
+    let mut wav = Vec::with_capacity(44 + data_len);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data_len as u32).to_le_bytes());
+    for n in 0..samples {
+        let t = n as f64 / sample_rate as f64;
+        let sample = ((t * 440.0 * std::f64::consts::TAU).sin() * 8000.0) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    std::fs::write(path, wav).unwrap();
+    data_len
+}
+
+async fn dg_stt(dir: &Path, api_key: Option<&str>, args: &[&str]) -> Output {
+    let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_dg-stt"));
+    command
+        .args(args)
+        .current_dir(dir)
+        .env_remove("DEEPGRAM_API_KEY")
+        .kill_on_drop(true);
+    if let Some(api_key) = api_key {
+        command.env("DEEPGRAM_API_KEY", api_key);
+    }
+    let output = tokio::time::timeout(Duration::from_secs(60), command.output())
+        .await
+        .expect("dg-stt did not finish within 60 seconds")
+        .unwrap();
+    Output {
+        status: output.status,
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+#[tokio::test]
+async fn streams_a_file_and_handles_results_events_and_metadata() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("stream");
+    let audio = dir.join("tone.wav");
+    let data_len = write_wav(&audio, 2.5);
+    let endpoint = mock.ws_url();
+
+    let output = dg_stt(
+        &dir,
+        Some("test-key"),
+        &[
+            "stream",
+            "file",
+            "--file",
+            audio.to_str().unwrap(),
+            "--fast",
+            "--interim-results",
+            "--utterance-end",
+            "1000",
+            "--vad-events",
+            "--transcript-log",
+            "results.jsonl",
+            "--endpoint",
+            &endpoint,
+        ],
+    )
+    .await;
+
+    assert!(output.status.success(), "stderr: {}", output.stderr);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("Request ID: mock-req-1"), "{stdout}");
+    for transcript in STREAM_SCRIPT {
+        assert!(stdout.contains(&format!("Transcript: {transcript} (Confidence: 98.0%)")));
+    }
+    // Control events carry `channel` as an array rather than the Results object.
+    assert!(stdout.contains("[SpeechStarted at 0.00s]"), "{stdout}");
+    assert!(stdout.contains("[UtteranceEnd, last word ended at 1.00s]"));
+    assert!(stdout.contains("[UtteranceEnd, last word ended at 2.00s]"));
+    assert!(stdout.contains("Metadata: {"));
+    assert!(!output.stderr.contains("Failed to parse response"));
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.path, "/v1/listen");
+    assert_eq!(request.authorization.as_deref(), Some("Token test-key"));
+    assert_eq!(request.param("encoding"), Some("linear16"));
+    assert_eq!(request.param("sample_rate"), Some("16000"));
+    assert_eq!(request.param("channels"), Some("1"));
+    assert_eq!(request.param("interim_results"), Some("true"));
+    assert_eq!(request.param("utterance_end_ms"), Some("1000"));
+    assert_eq!(request.audio_bytes, data_len);
+    assert_eq!(
+        request.control_messages.last().map(String::as_str),
+        Some(r#"{"type":"CloseStream"}"#)
+    );
+
+    // One line per final result: two whole seconds and the closing half second.
+    let log = std::fs::read_to_string(dir.join("results.jsonl")).unwrap();
+    let entries: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0]["request_id"], "mock-req-1");
+    assert_eq!(entries[0]["transcript"], STREAM_SCRIPT[0]);
+    assert_eq!(entries[2]["start"], 2.0);
+    assert_eq!(entries[2]["duration"], 0.5);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn streams_code_switched_audio_with_language_tags() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("multi");
+    let audio = dir.join("call.wav");
+    write_wav(&audio, 2.0);
+    let endpoint = mock.ws_url();
+
+    let output = dg_stt(
+        &dir,
+        None,
+        &[
+            "stream",
+            "file",
+            "--file",
+            audio.to_str().unwrap(),
+            "--fast",
+            "--language",
+            "multi",
+            "--transcript-log",
+            "results.jsonl",
+            "--endpoint",
+            &endpoint,
+        ],
+    )
+    .await;
+
+    assert!(output.status.success(), "stderr: {}", output.stderr);
+    let stdout = &output.stdout;
+    assert!(
+        stdout
+            .contains("Transcript: [es] hola buenos días [en] how can I help (Confidence: 98.0%)"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Transcript: [en] I need a refund [es] por favor"));
+
+    // Without --model, language=multi asks for a multilingual model.
+    let request = &mock.requests()[0];
+    assert_eq!(request.param("language"), Some("multi"));
+    assert_eq!(request.param("model"), Some("nova-3"));
+
+    let log = std::fs::read_to_string(dir.join("results.jsonl")).unwrap();
+    let first: serde_json::Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(first["words"][0]["language"], "es");
+    assert_eq!(first["words"][3]["language"], "en");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn stream_reports_a_rejected_handshake_with_its_request_id() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("rejected");
+    let audio = dir.join("tone.wav");
+    write_wav(&audio, 1.0);
+    let endpoint = mock.ws_url();
+
+    let output = dg_stt(
+        &dir,
+        None,
+        &[
+            "stream",
+            "file",
+            "--file",
+            audio.to_str().unwrap(),
+            "--fast",
+            "--model",
+            "nova-0",
+            "--endpoint",
+            &endpoint,
+        ],
+    )
+    .await;
+
+    let stderr = &output.stderr;
+    assert!(stderr.contains("Request ID: mock-req-1"), "{stderr}");
+    assert!(stderr.contains("Error 400 Bad Request: "), "{stderr}");
+    assert!(stderr.contains("No such model/language/tier combination found."));
+    assert!(!output.stdout.contains("Connected to Deepgram!"));
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].param("model"), Some("nova-0"));
+    assert_eq!(requests[0].authorization, None);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn transcribes_a_file_with_utterances_and_search_hits() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("transcribe");
+    let audio = dir.join("tone.wav");
+    write_wav(&audio, 2.0);
+    let file_len = std::fs::metadata(&audio).unwrap().len() as usize;
+    let endpoint = mock.http_url();
+
+    let output = dg_stt(
+        &dir,
+        None,
+        &[
+            "transcribe",
+            "--file",
+            audio.to_str().unwrap(),
+            "--utterances",
+            "--search",
+            "mock server",
+            "--search",
+            "refund",
+            "--endpoint",
+            &endpoint,
+        ],
+    )
+    .await;
+
+    assert!(output.status.success(), "stderr: {}", output.stderr);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("Request ID: mock-req-1"), "{stdout}");
+    assert!(stdout.contains("hello from the mock server."));
+    assert!(stdout.contains("[00:01.2 - 00:02.0] Speaker 1: how can i help"));
+    assert!(stdout.contains("=== Search Hits ==="));
+    assert!(
+        stdout.contains("\"mock server\": 1 hit(s)\n  [00:00.4 - 00:01.1] mock server"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("\"refund\": 0 hit(s)"), "{stdout}");
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(
+        (request.method.as_str(), request.path.as_str()),
+        ("POST", "/v1/listen")
+    );
+    assert_eq!(request.authorization, None);
+    assert_eq!(request.param("utterances"), Some("true"));
+    assert_eq!(request.audio_bytes, file_len);
+    let searches: Vec<&str> = request
+        .query
+        .iter()
+        .filter(|(name, _)| name == "search")
+        .map(|(_, value)| value.as_str())
+        .collect();
+    assert_eq!(searches, ["mock server", "refund"]);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn transcribe_writes_only_subtitles_to_stdout() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("subtitles");
+    let audio = dir.join("tone.wav");
+    write_wav(&audio, 2.0);
+    let endpoint = mock.http_url();
+
+    for (format, first_line) in [("vtt", "WEBVTT"), ("srt", "1")] {
+        let output = dg_stt(
+            &dir,
+            None,
+            &[
+                "transcribe",
+                "--file",
+                audio.to_str().unwrap(),
+                "--output",
+                format,
+                "--endpoint",
+                &endpoint,
+            ],
+        )
+        .await;
+
+        assert!(output.status.success(), "stderr: {}", output.stderr);
+        let stdout = &output.stdout;
+        assert_eq!(stdout.lines().next(), Some(first_line), "{stdout}");
+        assert!(stdout.contains("hello from the mock server"), "{stdout}");
+        assert!(output.stderr.contains("Sending request to Deepgram API..."));
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn transcribe_dry_run_does_not_bind_the_callback_listener() {
+    let dir = scratch_dir("dry-run-callback");
+    let audio = dir.join("tone.wav");
+    write_wav(&audio, 0.5);
+    // Hold the port, so binding it would fail.
+    let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = taken.local_addr().unwrap().to_string();
+
+    let output = dg_stt(
+        &dir,
+        None,
+        &[
+            "transcribe",
+            "--file",
+            audio.to_str().unwrap(),
+            "--dry-run",
+            "--endpoint",
+            "http://localhost:9",
+            "--listen-callback",
+            &addr,
+        ],
+    )
+    .await;
+
+    assert!(output.status.success(), "stderr: {}", output.stderr);
+    let callback = format!("http%3A%2F%2F{}%2Fcallback", addr.replace(':', "%3A"));
+    assert!(
+        output.stdout.contains(&format!(
+            "POST http://localhost:9/v1/listen?callback={callback}\n"
+        )),
+        "{}",
+        output.stdout
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn transcribe_detects_the_language_among_candidates() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("detect-language");
+    let audio = dir.join("tone.wav");
+    write_wav(&audio, 1.0);
+    let endpoint = mock.http_url();
+
+    let output = dg_stt(
+        &dir,
+        None,
+        &[
+            "transcribe",
+            "--file",
+            audio.to_str().unwrap(),
+            "--detect-language",
+            "es,en",
+            "--endpoint",
+            &endpoint,
+        ],
+    )
+    .await;
+
+    assert!(output.status.success(), "stderr: {}", output.stderr);
+    assert!(
+        output
+            .stdout
+            .contains("Detected Language: es (confidence: 93.0%)"),
+        "{}",
+        output.stdout
+    );
+    let candidates: Vec<(String, String)> = mock.requests()[0]
+        .query
+        .iter()
+        .filter(|(name, _)| name == "detect_language")
+        .cloned()
+        .collect();
+    assert_eq!(
+        candidates,
+        [
+            ("detect_language".to_string(), "es".to_string()),
+            ("detect_language".to_string(), "en".to_string())
+        ]
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn transcribe_fails_with_the_api_error_for_a_bad_key() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("unauthorized");
+    let audio = dir.join("tone.wav");
+    write_wav(&audio, 1.0);
+    let endpoint = mock.http_url();
+
+    let output = dg_stt(
+        &dir,
+        Some(BAD_API_KEY),
+        &[
+            "transcribe",
+            "--file",
+            audio.to_str().unwrap(),
+            "--endpoint",
+            &endpoint,
+        ],
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert!(
+        output
+            .stderr
+            .contains("API request failed with status 401 Unauthorized"),
+        "{}",
+        output.stderr
+    );
+    assert!(output.stderr.contains("INVALID_AUTH"));
+    assert_eq!(
+        mock.requests()[0].authorization.as_deref(),
+        Some("Token bad-key")
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn lists_models_with_and_without_outdated_versions() {
+    let mock = MockDeepgram::start().await;
+    let dir = scratch_dir("models");
+    let endpoint = mock.http_url();
+
+    let output = dg_stt(&dir, None, &["list-models", "--endpoint", &endpoint]).await;
+    assert!(output.status.success(), "stderr: {}", output.stderr);
+    let stdout = &output.stdout;
+    assert!(stdout.contains("Canonical Name"), "{stdout}");
+    assert!(stdout.contains("nova-3"));
+    assert!(stdout.contains("batch, streaming"));
+    assert!(stdout.contains("en, es, fr"));
+    assert!(!stdout.contains("2023-11-14.0"));
+
+    let output = dg_stt(
+        &dir,
+        None,
+        &["list-models", "--include-outdated", "--endpoint", &endpoint],
+    )
+    .await;
+    assert!(output.status.success(), "stderr: {}", output.stderr);
+    assert!(output.stdout.contains("2023-11-14.0"));
+
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].path, "/v1/models");
+    assert_eq!(requests[1].param("include_outdated"), Some("true"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,531 @@
+//! A scripted stand-in for the Deepgram API, so the end-to-end tests can run
+//! the real `dg-stt` binary against `--endpoint` without network access.
+//!
+//! - `GET /v1/listen` upgraded to a WebSocket: `SpeechStarted` on the first
+//!   audio with `vad_events=true`, then for every second of audio a final
+//!   `Results` message (preceded by an interim one with `interim_results=true`)
+//!   and an `UtteranceEnd` with `utterance_end_ms`. `Finalize` and
+//!   `CloseStream` flush the remaining audio as a final result; `CloseStream`
+//!   then sends `Metadata` and closes the socket.
+//!   With `language=multi`, transcripts switch between Spanish and English and
+//!   each word carries its `language`.
+//! - `POST /v1/listen`: a pre-recorded response, with utterances, search hits,
+//!   and a detected language when they are requested.
+//! - `GET /v1/models`: two current models, plus an outdated version with
+//!   `include_outdated=true`.
+//!
+//! Like the real API, `Authorization: Token bad-key` gets a 401 and a `model`
+//! it does not know gets a 400, both with a `dg-request-id` header and a JSON
+//! error body. Control events use the array `channel` shape and Results the
+//! object shape, as Deepgram sends them.
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::{Message, Role};
+
+/// One transcript per second of streamed audio, repeating.
+pub const STREAM_SCRIPT: [&str; 3] = [
+    "hello from the mock server",
+    "this is the second second",
+    "and this is the third",
+];
+
+/// One code-switched transcript per second of audio with `language=multi`,
+/// as (language, words) runs.
+pub const MULTILINGUAL_SCRIPT: [&[(&str, &str)]; 2] = [
+    &[("es", "hola buenos días"), ("en", "how can I help")],
+    &[("en", "I need a refund"), ("es", "por favor")],
+];
+
+pub const PRERECORDED_TRANSCRIPT: &str = "hello from the mock server. how can i help";
+
+/// The API key the mock rejects with a 401.
+pub const BAD_API_KEY: &str = "bad-key";
+
+const KNOWN_MODELS: [&str; 2] = ["nova-3", "nova-2"];
+
+/// A request the mock received.
+#[derive(Clone, Debug, Default)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub authorization: Option<String>,
+    /// The `dg-request-id` the mock answered with.
+    pub request_id: String,
+    /// HTTP body bytes, or the total of the binary WebSocket messages.
+    pub audio_bytes: usize,
+    /// Text messages received over the WebSocket, e.g. `{"type":"CloseStream"}`.
+    pub control_messages: Vec<String>,
+}
+
+impl Request {
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct MockDeepgram {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockDeepgram {
+    /// Serve on an ephemeral local port until the test's runtime shuts down.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let accept_requests = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let requests = accept_requests.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, requests).await;
+                });
+            }
+        });
+        MockDeepgram { addr, requests }
+    }
+
+    /// `--endpoint` for `transcribe` and `list-models`.
+    pub fn http_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// `--endpoint` for the streaming sources.
+    pub fn ws_url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// Every request received so far, in arrival order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    requests: Arc<Mutex<Vec<Request>>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&mut stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut request = Request {
+        method,
+        path: path.to_string(),
+        query: url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect(),
+        ..Request::default()
+    };
+    let mut websocket_key = None;
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("sec-websocket-key") {
+                websocket_key = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("authorization") {
+                request.authorization = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    request.audio_bytes = body.len();
+
+    let index = {
+        let mut requests = requests.lock().unwrap();
+        request.request_id = format!("mock-req-{}", requests.len() + 1);
+        requests.push(request.clone());
+        requests.len() - 1
+    };
+
+    if let Some((status, error)) = rejection(&request) {
+        let body = error.to_string();
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\ndg-request-id: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            request.request_id,
+            body.len()
+        );
+        return stream.write_all(response.as_bytes()).await;
+    }
+
+    let response = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/v1/listen") if websocket_key.is_some() => {
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\ndg-request-id: {}\r\n\r\n",
+                derive_accept_key(websocket_key.unwrap_or_default().as_bytes()),
+                request.request_id
+            );
+            stream.write_all(response.as_bytes()).await?;
+            let websocket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+            serve_stream(websocket, &request, &requests, index).await;
+            return Ok(());
+        }
+        ("POST", "/v1/listen") => json_response("200 OK", &prerecorded_response(&request)),
+        ("GET", "/v1/models") => json_response("200 OK", &models_response(&request)),
+        _ => json_response("404 Not Found", &json!({ "err_code": "NOT_FOUND" })),
+    };
+    stream.write_all(response.as_bytes()).await
+}
+
+/// The status line and error body the real API would reject `request` with.
+fn rejection(request: &Request) -> Option<(&'static str, Value)> {
+    if request.authorization.as_deref() == Some(&format!("Token {BAD_API_KEY}")) {
+        return Some((
+            "401 Unauthorized",
+            json!({
+                "err_code": "INVALID_AUTH",
+                "err_msg": "Invalid credentials.",
+                "request_id": request.request_id,
+            }),
+        ));
+    }
+    if request.path == "/v1/listen"
+        && let Some(model) = request.param("model")
+        && !KNOWN_MODELS.contains(&model)
+    {
+        return Some((
+            "400 Bad Request",
+            json!({
+                "err_code": "Bad Request",
+                "err_msg": "No such model/language/tier combination found.",
+                "request_id": request.request_id,
+            }),
+        ));
+    }
+    None
+}
+
+fn json_response(status: &str, body: &Value) -> String {
+    let body = body.to_string();
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// The scripted streaming session. Audio and control messages are added to
+/// `requests[index]` as they arrive.
+async fn serve_stream(
+    websocket: WebSocketStream<TcpStream>,
+    request: &Request,
+    requests: &Mutex<Vec<Request>>,
+    index: usize,
+) {
+    let record = |update: &dyn Fn(&mut Request)| update(&mut requests.lock().unwrap()[index]);
+    let (mut sink, mut incoming) = websocket.split();
+    let sample_rate: f64 = request
+        .param("sample_rate")
+        .and_then(|rate| rate.parse().ok())
+        .unwrap_or(16000.0);
+    let channels: f64 = request
+        .param("channels")
+        .and_then(|channels| channels.parse().ok())
+        .unwrap_or(1.0);
+    let bytes_per_sample = match request.param("encoding") {
+        Some("mulaw" | "alaw") => 1.0,
+        Some("linear32") => 4.0,
+        _ => 2.0,
+    };
+    let bytes_per_second = sample_rate * channels * bytes_per_sample;
+    let interim_results = request.param("interim_results") == Some("true");
+    let vad_events = request.param("vad_events") == Some("true");
+    let utterance_end = request.param("utterance_end_ms").is_some();
+    let diarize = request.param("diarize") == Some("true");
+
+    let mut received = 0usize;
+    // End of the audio already covered by a final result, and the number of
+    // final results sent, which picks the next scripted transcript.
+    let mut finalized = 0.0;
+    let mut finals = 0;
+    let mut interim_sent = false;
+
+    while let Some(Ok(message)) = incoming.next().await {
+        let mut replies = Vec::new();
+        let mut closing = false;
+        match message {
+            Message::Binary(audio) => {
+                if received == 0 && vad_events {
+                    replies.push(json!({
+                        "type": "SpeechStarted",
+                        "channel": [0, 1],
+                        "timestamp": 0.0,
+                    }));
+                }
+                received += audio.len();
+                record(&|request| request.audio_bytes += audio.len());
+                let position = received as f64 / bytes_per_second;
+                if interim_results && !interim_sent && position - finalized >= 0.5 {
+                    interim_sent = true;
+                    replies.push(results(
+                        request, finals, finalized, 0.5, false, false, diarize,
+                    ));
+                }
+                if position - finalized >= 1.0 {
+                    replies.push(results(
+                        request, finals, finalized, 1.0, true, false, diarize,
+                    ));
+                    finals += 1;
+                    finalized += 1.0;
+                    interim_sent = false;
+                    if utterance_end {
+                        replies.push(json!({
+                            "type": "UtteranceEnd",
+                            "channel": [0, 1],
+                            "last_word_end": finalized,
+                        }));
+                    }
+                }
+            }
+            Message::Text(text) => {
+                record(&|request| request.control_messages.push(text.to_string()));
+                let message_type = serde_json::from_str::<Value>(&text)
+                    .ok()
+                    .and_then(|message| message["type"].as_str().map(String::from))
+                    .unwrap_or_default();
+                if message_type == "Finalize" || message_type == "CloseStream" {
+                    let position = received as f64 / bytes_per_second;
+                    if position > finalized {
+                        replies.push(results(
+                            request,
+                            finals,
+                            finalized,
+                            position - finalized,
+                            true,
+                            message_type == "Finalize",
+                            diarize,
+                        ));
+                        finals += 1;
+                        finalized = position;
+                    }
+                }
+                if message_type == "CloseStream" {
+                    closing = true;
+                    replies.push(json!({
+                        "type": "Metadata",
+                        "request_id": request.request_id,
+                        "duration": finalized,
+                        "channels": channels,
+                    }));
+                }
+            }
+            Message::Close(_) => return,
+            _ => {}
+        }
+        for reply in replies {
+            if sink.send(Message::text(reply.to_string())).await.is_err() {
+                return;
+            }
+        }
+        if closing {
+            let _ = sink.send(Message::Close(None)).await;
+            return;
+        }
+    }
+}
+
+/// A Results message for `duration` seconds from `start`, with the scripted
+/// transcript's words spread evenly across them.
+fn results(
+    request: &Request,
+    index: usize,
+    start: f64,
+    duration: f64,
+    is_final: bool,
+    from_finalize: bool,
+    diarize: bool,
+) -> Value {
+    let segments: Vec<(Option<&str>, &str)> = if request.param("language") == Some("multi") {
+        MULTILINGUAL_SCRIPT[index % MULTILINGUAL_SCRIPT.len()]
+            .iter()
+            .map(|&(language, text)| (Some(language), text))
+            .collect()
+    } else {
+        vec![(None, STREAM_SCRIPT[index % STREAM_SCRIPT.len()])]
+    };
+    let transcript = segments
+        .iter()
+        .map(|(_, text)| *text)
+        .collect::<Vec<_>>()
+        .join(" ");
+    json!({
+        "type": "Results",
+        "channel_index": [0, 1],
+        "start": start,
+        "duration": duration,
+        "is_final": is_final,
+        "speech_final": is_final,
+        "from_finalize": from_finalize,
+        "channel": {
+            "alternatives": [{
+                "transcript": transcript,
+                "confidence": 0.98,
+                "words": words(&segments, start, duration, diarize),
+            }],
+        },
+        "metadata": { "request_id": request.request_id },
+    })
+}
+
+/// Words for (language, text) runs, spread evenly across `duration` seconds
+/// from `start`.
+fn words(
+    segments: &[(Option<&str>, &str)],
+    start: f64,
+    duration: f64,
+    diarize: bool,
+) -> Vec<Value> {
+    let words: Vec<(Option<&str>, &str)> = segments
+        .iter()
+        .flat_map(|&(language, text)| text.split_whitespace().map(move |word| (language, word)))
+        .collect();
+    let step = duration / words.len() as f64;
+    words
+        .iter()
+        .enumerate()
+        .map(|(index, &(language, word))| {
+            let mut word = json!({
+                "word": word,
+                "punctuated_word": word,
+                "start": start + index as f64 * step,
+                "end": start + (index + 1) as f64 * step,
+                "confidence": 0.98,
+            });
+            if diarize {
+                word["speaker"] = json!(index * 2 / words.len().max(1));
+            }
+            if let Some(language) = language {
+                word["language"] = json!(language);
+            }
+            word
+        })
+        .collect()
+}
+
+fn prerecorded_response(request: &Request) -> Value {
+    let duration = 2.0;
+    let words = words(&[(None, PRERECORDED_TRANSCRIPT)], 0.0, duration, true);
+    let searches: Vec<Value> = request
+        .query
+        .iter()
+        .filter(|(name, _)| name == "search")
+        .map(|(_, query)| {
+            let hits = if PRERECORDED_TRANSCRIPT.contains(query.as_str()) {
+                vec![json!({
+                    "confidence": 0.9,
+                    "start": 0.4,
+                    "end": 1.1,
+                    "snippet": query,
+                })]
+            } else {
+                Vec::new()
+            };
+            json!({ "query": query, "hits": hits })
+        })
+        .collect();
+
+    // With detect_language, the first candidate (or English) is "detected".
+    let detected_language = request
+        .query
+        .iter()
+        .find(|(name, _)| name == "detect_language")
+        .map(|(_, value)| {
+            if value == "true" {
+                "en"
+            } else {
+                value.as_str()
+            }
+        });
+
+    let mut results = json!({
+        "channels": [{
+            "detected_language": detected_language,
+            "language_confidence": detected_language.map(|_| 0.93),
+            "search": searches,
+            "alternatives": [{
+                "transcript": PRERECORDED_TRANSCRIPT,
+                "confidence": 0.97,
+                "words": words,
+            }],
+        }],
+    });
+    if request.param("utterances") == Some("true") {
+        let (first, second) = PRERECORDED_TRANSCRIPT.split_once(". ").unwrap();
+        results["utterances"] = json!([
+            { "start": 0.0, "end": 1.2, "transcript": format!("{first}."), "channel": 0, "speaker": 0 },
+            { "start": 1.2, "end": duration, "transcript": second, "channel": 0, "speaker": 1 },
+        ]);
+    }
+
+    json!({
+        "metadata": {
+            "request_id": request.request_id,
+            "duration": duration,
+            "channels": 1,
+        },
+        "results": results,
+    })
+}
+
+fn models_response(request: &Request) -> Value {
+    let mut stt = vec![
+        json!({
+            "canonical_name": "nova-3",
+            "version": "2025-04-17.0",
+            "uuid": "00000000-0000-0000-0000-000000000003",
+            "languages": ["en", "en-US"],
+            "batch": true,
+            "streaming": true,
+        }),
+        json!({
+            "canonical_name": "nova-2",
+            "version": "2024-01-09.0",
+            "uuid": "00000000-0000-0000-0000-000000000002",
+            "languages": ["en", "es", "fr", "de"],
+            "batch": true,
+            "streaming": false,
+        }),
+    ];
+    if request.param("include_outdated") == Some("true") {
+        stt.push(json!({
+            "canonical_name": "nova-2",
+            "version": "2023-11-14.0",
+            "uuid": "00000000-0000-0000-0000-000000000001",
+            "languages": ["en"],
+            "batch": true,
+            "streaming": true,
+        }));
+    }
+    json!({ "stt": stt })
+}